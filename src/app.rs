@@ -1,3 +1,5 @@
+use std::path::{Path, PathBuf};
+
 use clap::{Parser, ValueEnum};
 
 use crate::client::Nifi;
@@ -5,7 +7,11 @@ use crate::client::Nifi;
 #[derive(Clone, Copy, Debug, clap::ValueEnum)]
 pub enum Output {
     Turtle,
+    /// Raw json returned by nifi
     Json,
+    NTriples,
+    JsonLd,
+    RdfXml,
 }
 
 impl Default for Output {
@@ -14,6 +20,33 @@ impl Default for Output {
     }
 }
 
+/// Serialization of an input file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum InputFormat {
+    Turtle,
+    NTriples,
+    NQuads,
+    Trig,
+    JsonLd,
+    RdfXml,
+}
+
+impl InputFormat {
+    /// Guesses the format from the file extension
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "ttl" | "turtle" => Some(InputFormat::Turtle),
+            "nt" => Some(InputFormat::NTriples),
+            "nq" => Some(InputFormat::NQuads),
+            "trig" => Some(InputFormat::Trig),
+            "jsonld" | "json" => Some(InputFormat::JsonLd),
+            "rdf" | "owl" | "xml" => Some(InputFormat::RdfXml),
+            _ => None,
+        }
+    }
+}
+
 #[derive(clap::Args, Debug)]
 pub struct RunArgs {
     #[arg(value_enum, short, long, default_value_t = Output::default())]
@@ -29,6 +62,9 @@ pub struct Args {
     pub run: RunArgs,
     #[command(flatten)]
     pub client: Nifi,
+    /// Directory to cache generated ontologies, defaults to $XDG_CACHE_HOME/nifi-runner
+    #[arg(long)]
+    pub cache: Option<PathBuf>,
     #[command(subcommand)]
     pub action: Action,
 }
@@ -36,12 +72,43 @@ pub struct Args {
 #[derive(clap::Subcommand, Debug)]
 pub enum Action {
     Run {
-        #[arg(short, long, default_value_t = String::from("./ontology.ttl"))]
-        ontology: String,
+        /// Ontology describing the used nifi types, by default these are taken from the cache
+        #[arg(short, long)]
+        ontology: Option<String>,
         #[arg(short, long)]
         no_start: bool,
-        input: Option<String>,
+        /// Format of the input, by default guessed from the extension, stdin defaults to turtle.
+        /// JSON-LD is read without a full processor: remote contexts and named graphs give an
+        /// error
+        #[arg(long, value_enum)]
+        input_format: Option<InputFormat>,
+        /// Report all parse errors of the inputs, instead of stopping at the first one
+        #[arg(long)]
+        keep_going: bool,
+        /// Only deploy the named graph with this IRI, by default every named graph in the inputs
+        /// is deployed in its own process group
+        #[arg(long)]
+        pipeline: Option<String>,
+        /// Directory with custom channels, by default $XDG_CONFIG_HOME/nifi-runner/channels and
+        /// the channels directory next to the binary are used
+        #[arg(long)]
+        channels_dir: Option<PathBuf>,
+        /// Files or directories describing the pipeline, read from stdin when none are given.
+        /// Local files referenced with `owl:imports` are loaded as well
+        input: Vec<String>,
+    },
+    /// Convert an RDF document on stdin to another serialization, nifi runs this for channels
+    /// with a `:serialization`
+    Convert {
+        /// Content type of the input, an empty or unknown one is parsed as the output
+        #[arg(long)]
+        from: Option<String>,
+        /// Content type of the output, like `text/turtle`
+        #[arg(long)]
+        to: String,
     },
+    /// Export the current process group, so it can be passed to run
+    Export,
     /// Get information about the current nifi instance
     Info,
     /// List things
@@ -62,7 +129,7 @@ pub enum Actives {
 
 #[derive(clap::Subcommand, Debug)]
 pub enum ListAction {
-    /// List all currently supported nifi processors
+    /// List all currently supported nifi processors, `--full` also fills the ontology cache
     Types {
         #[arg(short, long)]
         filter: Option<Vec<String>>,
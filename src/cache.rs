@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::models::BundleDTO;
+
+const VERSION_FILE: &str = "nifi-version";
+const INDEX_FILE: &str = "index.json";
+const BUNDLES_DIR: &str = "bundles";
+
+/// Generated processor ontologies stored on disk per bundle, so `run` can find the ontology of
+/// every type it uses.
+///
+/// The cache only stays valid for the nifi version that filled it.
+pub struct OntologyCache {
+    dir: PathBuf,
+    /// Type IRI to the cached files (relative to `dir`) that describe it
+    index: HashMap<String, Vec<String>>,
+}
+
+pub fn default_cache_dir() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|x| PathBuf::from(x).join(".cache")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("nifi-runner")
+}
+
+impl OntologyCache {
+    /// Opens the cache in `dir`, the cached ontologies are dropped if they were generated by
+    /// another nifi version.
+    pub fn open<P: AsRef<Path>>(dir: P, nifi_version: &str) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let version_file = dir.join(VERSION_FILE);
+
+        match fs::read_to_string(&version_file) {
+            Ok(v) if v.trim() == nifi_version => {}
+            Ok(v) => {
+                eprintln!(
+                    "Ontology cache was filled by nifi {}, not {}, clearing it",
+                    v.trim(),
+                    nifi_version
+                );
+                let bundles = dir.join(BUNDLES_DIR);
+                if bundles.exists() {
+                    fs::remove_dir_all(bundles)?;
+                }
+                let _ = fs::remove_file(dir.join(INDEX_FILE));
+            }
+            Err(_) => {}
+        }
+
+        fs::create_dir_all(&dir)?;
+        fs::write(&version_file, nifi_version)?;
+
+        let index = fs::read(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|x| serde_json::from_slice(&x).ok())
+            .unwrap_or_default();
+
+        Ok(Self { dir, index })
+    }
+
+    /// Stores the ontology of one type of a bundle
+    pub fn store(&mut self, bundle: &BundleDTO, ty_iri: &str, content: &[u8]) -> io::Result<()> {
+        let name = ty_iri.rsplit(['#', '/']).next().unwrap_or(ty_iri);
+        let location = Path::new(BUNDLES_DIR)
+            .join(&bundle.group)
+            .join(&bundle.artifact)
+            .join(&bundle.version)
+            .join(format!("{}.ttl", name));
+
+        let path = self.dir.join(&location);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, content)?;
+
+        let location = location.to_string_lossy().to_string();
+        let files = self.index.entry(ty_iri.to_string()).or_default();
+        if !files.contains(&location) {
+            files.push(location);
+        }
+
+        fs::write(
+            self.dir.join(INDEX_FILE),
+            serde_json::to_vec_pretty(&self.index)?,
+        )
+    }
+
+    /// All cached ontology files that describe this type
+    pub fn lookup(&self, ty_iri: &str) -> Vec<PathBuf> {
+        self.index
+            .get(ty_iri)
+            .into_iter()
+            .flatten()
+            .map(|x| self.dir.join(x))
+            .filter(|x| x.exists())
+            .collect()
+    }
+}
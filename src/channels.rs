@@ -0,0 +1,163 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use oxigraph::store::Store;
+
+use crate::logic::{
+    directory_files, file_path, import_ontology, import_ontology_content, ChannelPlugins,
+    ParseErrors, ParseOptions, TemplateChannel,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Extensions of the ontologies in a channel directory, templates next to them are `.xml` files
+const ONTOLOGY_EXTENSIONS: &[&str] = &["ttl", "nt", "nq", "trig", "jsonld"];
+
+/// Base IRI of the embedded channel assets, relative template IRIs in the embedded ontologies
+/// resolve to an embedded template
+const EMBEDDED_BASE: &str = "nifi-runner:/channels/";
+
+/// The stock channels that are a nifi template
+pub static TEMPLATE_CHANNELS: &[TemplateChannel] = &[
+    TemplateChannel {
+        ontology: Some((
+            "ws_ontology.ttl",
+            include_str!("../channels/ws_ontology.ttl"),
+        )),
+        types: &[
+            "https://w3id.org/conn#WsReaderChannel",
+            "https://w3id.org/conn#WsWriterChannel",
+        ],
+    },
+    TemplateChannel {
+        ontology: Some((
+            "http_ontology.ttl",
+            include_str!("../channels/http_ontology.ttl"),
+        )),
+        types: &[
+            "https://w3id.org/conn#HttpReaderChannel",
+            "https://w3id.org/conn#HttpWriterChannel",
+        ],
+    },
+    TemplateChannel {
+        ontology: Some((
+            "kafka_ontology.ttl",
+            include_str!("../channels/kafka_ontology.ttl"),
+        )),
+        types: &[
+            "https://w3id.org/conn#KafkaReaderChannel",
+            "https://w3id.org/conn#KafkaWriterChannel",
+        ],
+    },
+    TemplateChannel {
+        ontology: Some((
+            "file_ontology.ttl",
+            include_str!("../channels/file_ontology.ttl"),
+        )),
+        types: &[
+            "https://w3id.org/conn#FileReaderChannel",
+            "https://w3id.org/conn#FileWriterChannel",
+        ],
+    },
+];
+
+static TEMPLATES: &[(&str, &str)] = &[
+    ("WSReader.xml", include_str!("../channels/WSReader.xml")),
+    ("WSWriter.xml", include_str!("../channels/WSWriter.xml")),
+    (
+        "HTTPPostReader.xml",
+        include_str!("../channels/HTTPPostReader.xml"),
+    ),
+    (
+        "HTTPPostWriter.xml",
+        include_str!("../channels/HTTPPostWriter.xml"),
+    ),
+    (
+        "KafkaReader.xml",
+        include_str!("../channels/KafkaReader.xml"),
+    ),
+    (
+        "KafkaWriter.xml",
+        include_str!("../channels/KafkaWriter.xml"),
+    ),
+    ("FileReader.xml", include_str!("../channels/FileReader.xml")),
+    ("FileWriter.xml", include_str!("../channels/FileWriter.xml")),
+];
+
+/// Directories with custom channels, `--channels-dir` when given, otherwise
+/// $XDG_CONFIG_HOME/nifi-runner/channels and the channels directory next to the binary
+pub fn channel_dirs(channels_dir: Option<PathBuf>) -> Vec<PathBuf> {
+    if let Some(dir) = channels_dir {
+        return vec![dir];
+    }
+
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|x| PathBuf::from(x).join(".config")))
+        .map(|x| x.join("nifi-runner").join("channels"));
+    let binary = std::env::current_exe()
+        .ok()
+        .and_then(|x| x.parent().map(|x| x.join("channels")));
+
+    config
+        .into_iter()
+        .chain(binary)
+        .filter(|x| x.is_dir())
+        .collect()
+}
+
+/// Loads the ontologies of the channel directories and of the channel plugins into the ontology
+/// graph, templates are only read when a channel is created
+pub fn append_ontologies(
+    dirs: &[PathBuf],
+    plugins: &ChannelPlugins,
+    store: &Store,
+) -> Result<(), ParseErrors> {
+    let mut files = Vec::new();
+    for dir in dirs {
+        directory_files(dir, ONTOLOGY_EXTENSIONS, &mut files)
+            .map_err(|e| ParseErrors::io(&dir.display().to_string(), e))?;
+    }
+
+    for (name, content) in plugins.ontologies() {
+        if files
+            .iter()
+            .any(|x| x.file_name().map_or(false, |x| x == name))
+        {
+            continue;
+        }
+
+        let base = format!("{}{}", EMBEDDED_BASE, name);
+        import_ontology_content(content.as_bytes(), name, &base, store)?;
+    }
+
+    for file in files {
+        println!("Loaded channel ontology {}", file.display());
+        import_ontology(file, &ParseOptions::default(), store)?;
+    }
+
+    Ok(())
+}
+
+/// Content of the template an IRI refers to, either a local file or an embedded template
+pub fn read_template(iri: &str) -> io::Result<String> {
+    if let Some(name) = iri.strip_prefix(EMBEDDED_BASE) {
+        return TEMPLATES
+            .iter()
+            .find(|x| x.0 == name)
+            .map(|x| x.1.to_string())
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("No template {}", iri))
+            });
+    }
+
+    match file_path(iri) {
+        Some(path) => fs::read_to_string(path),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Template {} is not a local file", iri),
+        )),
+    }
+}
@@ -0,0 +1,65 @@
+use std::fs;
+
+use oxigraph::model::{GraphNameRef, NamedNodeRef, QuadRef};
+use oxigraph::store::Store;
+
+use super::append_ontologies;
+use crate::logic::{ChannelPlugins, ONTOLOGY_GRAPH};
+
+const TEMPLATE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<template encoding-version="1.3">
+    <name>MyReader</name>
+    <snippet/>
+</template>
+"#;
+
+fn contains(store: &Store, subject: &str, predicate: &str, object: &str) -> bool {
+    store
+        .contains(QuadRef::new(
+            NamedNodeRef::new(subject).unwrap(),
+            NamedNodeRef::new(predicate).unwrap(),
+            NamedNodeRef::new(object).unwrap(),
+            GraphNameRef::NamedNode(NamedNodeRef::new(ONTOLOGY_GRAPH).unwrap()),
+        ))
+        .unwrap()
+}
+
+#[test]
+fn channel_directories_skip_templates() {
+    let dir = std::env::temp_dir().join(format!("nifi-runner-channels-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    // Replaces the embedded websocket ontology
+    fs::write(
+        dir.join("ws_ontology.ttl"),
+        "<https://w3id.org/conn#MyReaderChannel> a <http://www.w3.org/2000/01/rdf-schema#Class>.",
+    )
+    .unwrap();
+    fs::write(dir.join("MyReader.xml"), TEMPLATE).unwrap();
+
+    let store = Store::new().unwrap();
+    append_ontologies(&[dir.clone()], &ChannelPlugins::default(), &store).unwrap();
+
+    let sub_class = "http://www.w3.org/2000/01/rdf-schema#subClassOf";
+    assert!(contains(
+        &store,
+        "https://w3id.org/conn#MyReaderChannel",
+        "http://www.w3.org/1999/02/22-rdf-syntax-ns#type",
+        "http://www.w3.org/2000/01/rdf-schema#Class",
+    ));
+    assert!(!contains(
+        &store,
+        "https://w3id.org/conn#WsWriterChannel",
+        sub_class,
+        "https://w3id.org/conn#WriterChannel",
+    ));
+    assert!(contains(
+        &store,
+        "https://w3id.org/conn#HttpWriterChannel",
+        sub_class,
+        "https://w3id.org/conn#WriterChannel",
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
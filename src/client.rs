@@ -1,12 +1,14 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use feignhttp::{feign, Feign};
 use serde_json::Value;
 
 use crate::models::{
-    ConnectionEntity, ControllerServiceEntity, ControllerServiceTypesEntity,
-    ControllerServicesEntity, FlowEntity, PortEntity, PortsEntity, ProcessGroupEntity,
-    ProcessTypesEntity, ProcessorEntity, ServiceRunStatus, Variable, VariableDTO,
+    BundleDTO, Component, ConnectionEntity, ControllerServiceDTO, ControllerServiceDefinition,
+    ControllerServiceEntity, ControllerServiceTypesEntity, ControllerServicesEntity,
+    DocumentedTypeDTO, FlowEntity, FunnelEntity, PortEntity, PortsEntity, ProcessGroupEntity,
+    ProcessGroupFlowEntity, ProcessTypesEntity, ProcessorDTO, ProcessorDefinition, ProcessorEntity,
+    RemotePortDTO, RemoteProcessGroupEntity, ServiceRunStatus, Variable, VariableDTO,
     VariableRegistryEntity,
 };
 
@@ -19,17 +21,94 @@ pub struct Nifi {
     #[arg(short, long, default_value_t = String::from("root"))]
     #[feign_path]
     pub group: String,
+    /// Path of this binary on the nifi host, nifi runs its `convert` command to convert the flow
+    /// files of channels with a `:serialization`
+    #[arg(long)]
+    converter: Option<String>,
+    /// Environment variable with an access token of the nifi instance, like the one
+    /// `/access/token` returns
+    #[arg(long)]
+    credentials: Option<String>,
+    /// Bearer token of the credentials, empty without credentials
+    #[arg(skip)]
+    #[header]
+    authorization: String,
 }
 
 impl Nifi {
+    pub fn new(
+        nifi: &str,
+        group: &str,
+        converter: Option<&str>,
+        credentials: Option<&str>,
+    ) -> Result<Self, String> {
+        Nifi {
+            nifi: nifi.to_string(),
+            group: group.to_string(),
+            converter: converter.map(String::from),
+            credentials: credentials.map(String::from),
+            authorization: String::new(),
+        }
+        .authorize()
+    }
+
+    /// Reads the access token of the credentials, every request sends it
+    pub fn authorize(mut self) -> Result<Self, String> {
+        if let Some(credentials) = &self.credentials {
+            let token = std::env::var(credentials).map_err(|_| {
+                format!(
+                    "Credentials {} of {} are not set, export an access token in it",
+                    credentials, self.nifi
+                )
+            })?;
+            self.authorization = format!("Bearer {}", token);
+        }
+
+        Ok(self)
+    }
+
+    pub fn url(&self) -> &str {
+        &self.nifi
+    }
+
+    pub fn converter(&self) -> Option<&str> {
+        self.converter.as_deref()
+    }
+
     pub fn change_group(&self, group: &str) -> Self {
         Nifi {
             nifi: self.nifi.clone(),
             group: group.to_string(),
+            converter: self.converter.clone(),
+            credentials: self.credentials.clone(),
+            authorization: self.authorization.clone(),
         }
     }
 }
 
+/// Without a bundle nifi picks one when a type exists in multiple bundles
+fn component_type(ty: &str, bundle: Option<&BundleDTO>) -> Value {
+    let mut component = serde_json::json!({ "type": ty });
+    if let Some(bundle) = bundle {
+        component["bundle"] = serde_json::json!(bundle);
+    }
+    component
+}
+
+/// Nifi versions without an endpoint answer 404 or 405, other errors are real failures
+fn is_missing_endpoint(e: &feignhttp::Error) -> bool {
+    matches!(e.status().map(|x| x.as_u16()), Some(404) | Some(405))
+}
+
+/// The apis of a service type, when the description of the service does not list them
+fn with_apis(mut service: ControllerServiceDTO, ty: &DocumentedTypeDTO) -> ControllerServiceDTO {
+    if service.apis.iter().flatten().next().is_none() {
+        service.apis = ty.apis.clone();
+    }
+    service
+}
+
+#[derive(Clone, Copy)]
 pub enum PortType {
     Input,
     Output,
@@ -54,9 +133,32 @@ impl Nifi {
     #[get("/flow/controller-service-types")]
     pub async fn list_services_types(&self) -> feignhttp::Result<ControllerServiceTypesEntity> {}
 
+    #[get("/flow/processor-definition/{bundle_group}/{artifact}/{version}/{ty}")]
+    pub async fn get_processor_definition(
+        &self,
+        #[path] bundle_group: &str,
+        #[path] artifact: &str,
+        #[path] version: &str,
+        #[path] ty: &str,
+    ) -> feignhttp::Result<ProcessorDefinition> {
+    }
+
+    #[get("/flow/controller-service-definition/{bundle_group}/{artifact}/{version}/{ty}")]
+    pub async fn get_service_definition(
+        &self,
+        #[path] bundle_group: &str,
+        #[path] artifact: &str,
+        #[path] version: &str,
+        #[path] ty: &str,
+    ) -> feignhttp::Result<ControllerServiceDefinition> {
+    }
+
     #[get("/process-groups/{group}")]
     pub async fn get_process_group(&self) -> feignhttp::Result<ProcessGroupEntity> {}
 
+    #[get("/flow/process-groups/{group}")]
+    pub async fn get_flow(&self) -> feignhttp::Result<ProcessGroupFlowEntity> {}
+
     #[get("/process-groups/{group}/processors")]
     pub async fn list_active_processors(&self) -> feignhttp::Result<Value> {}
 
@@ -141,6 +243,48 @@ impl Nifi {
     ) -> feignhttp::Result<PortEntity> {
     }
 
+    #[post("/process-groups/{group}/funnels")]
+    pub async fn create_funnel(&self, #[body] body: Value) -> feignhttp::Result<FunnelEntity> {}
+
+    #[post("/process-groups/{group}/remote-process-groups")]
+    pub async fn create_remote_process_group(
+        &self,
+        #[body] body: Value,
+    ) -> feignhttp::Result<RemoteProcessGroupEntity> {
+    }
+
+    #[get("/remote-process-groups/{id}")]
+    pub async fn get_remote_process_group(
+        &self,
+        #[path] id: &str,
+    ) -> feignhttp::Result<RemoteProcessGroupEntity> {
+    }
+
+    #[put("/remote-process-groups/{id}/run-status")]
+    pub async fn update_remote_process_group_run_status(
+        &self,
+        #[path] id: &str,
+        #[body] body: Value,
+    ) -> feignhttp::Result<Value> {
+    }
+
+    #[get("/{ty}-ports/{id}")]
+    pub async fn get_port(
+        &self,
+        #[path] ty: PortType,
+        #[path] id: &str,
+    ) -> feignhttp::Result<PortEntity> {
+    }
+
+    #[put("/{ty}-ports/{id}/run-status")]
+    pub async fn update_port_run_status(
+        &self,
+        #[path] ty: PortType,
+        #[path] id: &str,
+        #[body] body: Value,
+    ) -> feignhttp::Result<Value> {
+    }
+
     #[get("/processors/{id}")]
     pub async fn get_processor(&self, #[path] id: &str) -> feignhttp::Result<ProcessorEntity> {}
 
@@ -179,6 +323,14 @@ impl Nifi {
     #[delete("/templates/{id}")]
     pub async fn delete_template(&self, #[path] id: &str) -> feignhttp::Result<Value> {}
 
+    #[delete("/process-groups/{id}")]
+    pub async fn delete_group(
+        &self,
+        #[path] id: &str,
+        #[query] version: u32,
+    ) -> feignhttp::Result<Value> {
+    }
+
     #[post("/process-groups/{group}/connections")]
     pub async fn create_conection(
         &self,
@@ -188,6 +340,21 @@ impl Nifi {
 }
 
 impl Nifi {
+    /// Deletes the process group of this client with everything in it
+    pub async fn delete_process_group(&self) -> feignhttp::Result<()> {
+        let group = self.get_process_group().await?;
+        self.delete_group(&group.id, group.revision.version).await?;
+        Ok(())
+    }
+
+    pub async fn nifi_version(&self) -> feignhttp::Result<String> {
+        let info = self.get_info().await?;
+        Ok(info["about"]["version"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string())
+    }
+
     pub async fn new_process_group(&self, name: &str) -> feignhttp::Result<ProcessGroupEntity> {
         let body = serde_json::json!({
             "revision": {"version": 0},
@@ -196,24 +363,86 @@ impl Nifi {
         self.create_process_group(body).await
     }
 
-    pub async fn new_processor(&self, ty: &str) -> feignhttp::Result<ProcessorEntity> {
+    pub async fn new_processor(
+        &self,
+        ty: &str,
+        bundle: Option<&BundleDTO>,
+    ) -> feignhttp::Result<ProcessorEntity> {
         let body = serde_json::json!({
             "revision": {"version": 0},
-            "component": {"type": ty}
+            "component": component_type(ty, bundle)
         });
 
         self.create_processor(body).await
     }
 
-    pub async fn new_service(&self, ty: &str) -> feignhttp::Result<ControllerServiceEntity> {
+    pub async fn new_service(
+        &self,
+        ty: &str,
+        bundle: Option<&BundleDTO>,
+    ) -> feignhttp::Result<ControllerServiceEntity> {
         let body = serde_json::json!({
             "revision": {"version": 0},
-            "component": {"type": ty}
+            "component": component_type(ty, bundle)
         });
 
         self.create_service(body).await
     }
 
+    /// Describes a processor type using the read-only definition endpoint.
+    /// Older nifi versions don't have this endpoint, then a processor is created and deleted again.
+    pub async fn describe_processor(
+        &self,
+        ty: &DocumentedTypeDTO,
+    ) -> feignhttp::Result<ProcessorDTO> {
+        if let Some(ref bundle) = ty.bundle {
+            match self
+                .get_processor_definition(&bundle.group, &bundle.artifact, &bundle.version, &ty.ty)
+                .await
+            {
+                Ok(def) => return Ok(def.into()),
+                Err(e) if is_missing_endpoint(&e) => eprintln!(
+                    "Processor definition of {} not available, creating processor instead\n{:?}",
+                    ty.ty, e
+                ),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let processor = self.new_processor(&ty.ty, ty.bundle.as_ref()).await?;
+        self.delete_processor(&processor.id, processor.revision.version)
+            .await?;
+
+        Ok(processor.component.comp)
+    }
+
+    /// Describes a controller service type using the read-only definition endpoint.
+    /// Older nifi versions don't have this endpoint, then a service is created and deleted again.
+    pub async fn describe_service(
+        &self,
+        ty: &DocumentedTypeDTO,
+    ) -> feignhttp::Result<ControllerServiceDTO> {
+        if let Some(ref bundle) = ty.bundle {
+            match self
+                .get_service_definition(&bundle.group, &bundle.artifact, &bundle.version, &ty.ty)
+                .await
+            {
+                Ok(def) => return Ok(with_apis(def.into(), ty)),
+                Err(e) if is_missing_endpoint(&e) => eprintln!(
+                    "Service definition of {} not available, creating service instead\n{:?}",
+                    ty.ty, e
+                ),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let service = self.new_service(&ty.ty, ty.bundle.as_ref()).await?;
+        self.delete_service(&service.id, service.revision.version)
+            .await?;
+
+        Ok(with_apis(service.component.comp, ty))
+    }
+
     pub async fn new_port(&self, ty: PortType, name: &str) -> feignhttp::Result<PortEntity> {
         let ty_str = ty.to_string();
         let body = serde_json::json!({
@@ -224,6 +453,78 @@ impl Nifi {
         self.create_port(ty, body).await
     }
 
+    pub async fn new_funnel(&self) -> feignhttp::Result<FunnelEntity> {
+        let body = serde_json::json!({
+            "revision": {"version": 0},
+            "component": {}
+        });
+        self.create_funnel(body).await
+    }
+
+    /// Input port that other nifi instances can send to with site-to-site
+    pub async fn new_public_port(&self, name: &str) -> feignhttp::Result<PortEntity> {
+        let body = serde_json::json!({
+            "revision": {"version": 0},
+            "component": {"name": name, "allowRemoteAccess": true}
+        });
+        self.create_port(PortType::Input, body).await
+    }
+
+    pub async fn new_remote_process_group(
+        &self,
+        url: &str,
+        protocol: &str,
+    ) -> feignhttp::Result<RemoteProcessGroupEntity> {
+        let body = serde_json::json!({
+            "revision": {"version": 0},
+            "component": {"targetUris": url, "transportProtocol": protocol}
+        });
+        self.create_remote_process_group(body).await
+    }
+
+    /// Nifi fetches the ports of the remote instance in the background, wait until the input port
+    /// with this name shows up, checking every 500 ms for at most `timeout`
+    pub async fn wait_for_remote_port(
+        &self,
+        id: &str,
+        name: &str,
+        timeout: Duration,
+    ) -> Result<Component<RemotePortDTO>, String> {
+        println!(
+            "Waiting at most {} s for remote port {}",
+            timeout.as_secs(),
+            name
+        );
+
+        let started = Instant::now();
+        loop {
+            let remote = self
+                .get_remote_process_group(id)
+                .await
+                .map_err(|e| format!("Failed to get remote process group {}\n{:?}", id, e))?;
+            let remote = remote.component.comp;
+            if let Some(port) = remote.input_port(name) {
+                return Ok(port);
+            }
+
+            if started.elapsed() >= timeout {
+                let reason = match remote.contents {
+                    None => "the remote instance is not reachable",
+                    Some(_) => "the remote instance has no public input port with that name",
+                };
+                return Err(format!(
+                    "No remote port {} on {} after {} s, {}",
+                    name,
+                    remote.target_uris,
+                    timeout.as_secs(),
+                    reason
+                ));
+            }
+
+            tokio::time::sleep(Duration::from_millis(500)).await
+        }
+    }
+
     pub async fn set_variables(
         &self,
         vars: impl Iterator<Item = (String, String)>,
@@ -330,8 +631,36 @@ impl Nifi {
 
         self.update_process_group(body).await?;
 
+        let flow = self.get_flow().await?;
+        for remote in flow.group_flow.flow.remote_process_groups {
+            println!(
+                "Enabling transmission to {}",
+                remote.component.comp.target_uris
+            );
+            let body = serde_json::json!({
+                "revision": remote.revision,
+                "state": "TRANSMITTING",
+            });
+
+            self.update_remote_process_group_run_status(&remote.id, body)
+                .await?;
+        }
+
         Ok(())
     }
+    pub async fn start_port(&self, ty: PortType, id: &str) -> feignhttp::Result<()> {
+        let port = self.get_port(ty, id).await?;
+
+        let body = serde_json::json!({
+            "revision": port.revision,
+            "state": "RUNNING",
+        });
+
+        self.update_port_run_status(ty, id, body).await?;
+
+        Ok(())
+    }
+
     pub async fn start_processor(&self, id: &str) -> feignhttp::Result<()> {
         let proc = self.get_processor(id).await?;
 
@@ -0,0 +1,205 @@
+use oxigraph::model::Literal;
+
+use crate::models::DescriptorDTO;
+use crate::rdf::prefix::{Prefix, NIFI, RDF, RDFS, XSD};
+use crate::rdf::{iri, RdfGraph};
+
+#[cfg(test)]
+mod tests;
+
+/// Declares the custom datatypes used in generated ontologies
+pub fn declare(graph: &mut RdfGraph) {
+    let types = [
+        (
+            "TimePeriod",
+            "Time period",
+            "A nifi time period, a number followed by a time unit, like '30 sec' or '5 mins'",
+        ),
+        (
+            "DataSize",
+            "Data size",
+            "A nifi data size, a number followed by a data unit, like '1 GB'",
+        ),
+    ];
+
+    for (name, label, comment) in types {
+        let ty = iri(&NIFI, name);
+        graph.add(ty.clone(), iri(&RDF, "type"), iri(&RDFS, "Datatype"));
+        graph.add(
+            ty.clone(),
+            iri(&RDFS, "label"),
+            Literal::new_simple_literal(label),
+        );
+        graph.add(
+            ty,
+            iri(&RDFS, "comment"),
+            Literal::new_simple_literal(comment),
+        );
+    }
+}
+
+const TIME_UNITS: &[(&[&str], &str)] = &[
+    (
+        &["ns", "nano", "nanos", "nanosecond", "nanoseconds"],
+        "nanos",
+    ),
+    (
+        &["ms", "milli", "millis", "millisecond", "milliseconds"],
+        "millis",
+    ),
+    (&["s", "sec", "secs", "second", "seconds"], "secs"),
+    (&["m", "min", "mins", "minute", "minutes"], "mins"),
+    (&["h", "hr", "hrs", "hour", "hours"], "hours"),
+    (&["d", "day", "days"], "days"),
+    (&["w", "wk", "wks", "week", "weeks"], "weeks"),
+];
+
+const DATA_UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Datatype {
+    String,
+    Integer,
+    Boolean,
+    TimePeriod,
+    DataSize,
+}
+
+impl Datatype {
+    fn prefix_and_name(self) -> (Prefix, &'static str) {
+        match self {
+            Datatype::String => (XSD, "string"),
+            Datatype::Integer => (XSD, "integer"),
+            Datatype::Boolean => (XSD, "boolean"),
+            Datatype::TimePeriod => (NIFI, "TimePeriod"),
+            Datatype::DataSize => (NIFI, "DataSize"),
+        }
+    }
+
+    pub fn iri(self) -> String {
+        let (prefix, name) = self.prefix_and_name();
+        format!("{}{}", prefix.1, name)
+    }
+
+    pub fn from_iri(iri: &str) -> Option<Self> {
+        [
+            Datatype::String,
+            Datatype::Integer,
+            Datatype::Boolean,
+            Datatype::TimePeriod,
+            Datatype::DataSize,
+        ]
+        .into_iter()
+        .find(|x| x.iri() == iri)
+    }
+
+    /// Guesses the datatype of a property from its validator, allowable values and default value
+    pub fn infer(descriptor: &DescriptorDTO) -> Self {
+        if let Some(ref validator) = descriptor.validator {
+            let validator = validator.to_uppercase();
+            if validator.contains("TIME_PERIOD") {
+                return Datatype::TimePeriod;
+            }
+            if validator.contains("DATA_SIZE") {
+                return Datatype::DataSize;
+            }
+            if validator.contains("BOOLEAN") {
+                return Datatype::Boolean;
+            }
+            if ["INTEGER", "LONG", "PORT"]
+                .iter()
+                .any(|x| validator.contains(x))
+            {
+                return Datatype::Integer;
+            }
+        }
+
+        if let Some(ref values) = descriptor.allowable_values {
+            let boolean = !values.is_empty()
+                && values
+                    .iter()
+                    .all(|x| matches!(x.allowable_value.value.as_str(), "true" | "false"));
+
+            return if boolean {
+                Datatype::Boolean
+            } else {
+                Datatype::String
+            };
+        }
+
+        match descriptor.default_value {
+            Some(ref value) => [
+                Datatype::Integer,
+                Datatype::Boolean,
+                Datatype::TimePeriod,
+                Datatype::DataSize,
+            ]
+            .into_iter()
+            .find(|x| x.normalize(value).is_ok())
+            .unwrap_or(Datatype::String),
+            None => Datatype::String,
+        }
+    }
+
+    /// Checks the value against this datatype, and returns the form nifi expects
+    pub fn normalize(self, value: &str) -> Result<String, String> {
+        let trimmed = value.trim();
+
+        match self {
+            Datatype::String => Ok(value.to_string()),
+            Datatype::Integer => trimmed
+                .parse::<i64>()
+                .map(|x| x.to_string())
+                .map_err(|_| format!("{:?} is not an integer", value)),
+            Datatype::Boolean => match trimmed.to_lowercase().as_str() {
+                "true" | "1" => Ok(String::from("true")),
+                "false" | "0" => Ok(String::from("false")),
+                _ => Err(format!("{:?} is not a boolean", value)),
+            },
+            Datatype::TimePeriod => {
+                let (amount, unit) = split_amount(trimmed)
+                    .ok_or_else(|| format!("{:?} is not a time period", value))?;
+                let unit = unit.to_lowercase();
+
+                TIME_UNITS
+                    .iter()
+                    .find(|(units, _)| units.contains(&unit.as_str()))
+                    .map(|(_, canonical)| format!("{} {}", amount, canonical))
+                    .ok_or_else(|| format!("{:?} is not a known time unit", unit))
+            }
+            Datatype::DataSize => {
+                let (amount, unit) = split_amount(trimmed)
+                    .ok_or_else(|| format!("{:?} is not a data size", value))?;
+                let unit = unit.to_uppercase();
+
+                if DATA_UNITS.contains(&unit.as_str()) {
+                    Ok(format!("{} {}", amount, unit))
+                } else {
+                    Err(format!("{:?} is not a known data unit", unit))
+                }
+            }
+        }
+    }
+}
+
+/// Splits "30 sec" or "1GB" into the decimal amount and the unit
+fn split_amount(value: &str) -> Option<(&str, &str)> {
+    let idx = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(idx);
+
+    if amount.is_empty() || amount.starts_with('.') || amount.ends_with('.') {
+        return None;
+    }
+    if amount.matches('.').count() > 1 {
+        return None;
+    }
+
+    let unit = unit.trim();
+    if unit.is_empty() {
+        return None;
+    }
+
+    Some((amount, unit))
+}
@@ -0,0 +1,137 @@
+use crate::models::DescriptorDTO;
+
+use super::{split_amount, Datatype};
+
+fn descriptor(validator: Option<&str>, allowed: &[&str], default: Option<&str>) -> DescriptorDTO {
+    let allowed: Option<Vec<_>> = (!allowed.is_empty()).then(|| {
+        allowed
+            .iter()
+            .map(|x| serde_json::json!({ "allowableValue": { "value": x } }))
+            .collect()
+    });
+
+    serde_json::from_value(serde_json::json!({
+        "name": "prop",
+        "displayName": "Prop",
+        "description": "",
+        "required": false,
+        "validator": validator,
+        "allowableValues": allowed,
+        "defaultValue": default,
+    }))
+    .unwrap()
+}
+
+#[test]
+fn infers_from_validator() {
+    let cases = [
+        ("TIME_PERIOD_VALIDATOR", Datatype::TimePeriod),
+        ("DATA_SIZE_VALIDATOR", Datatype::DataSize),
+        ("BOOLEAN_VALIDATOR", Datatype::Boolean),
+        ("POSITIVE_INTEGER_VALIDATOR", Datatype::Integer),
+        ("LONG_VALIDATOR", Datatype::Integer),
+        ("PORT_VALIDATOR", Datatype::Integer),
+        ("NON_EMPTY_VALIDATOR", Datatype::String),
+    ];
+
+    for (validator, expected) in cases {
+        let actual = Datatype::infer(&descriptor(Some(validator), &[], None));
+        assert_eq!(actual, expected, "{}", validator);
+    }
+}
+
+#[test]
+fn infers_from_allowable_values() {
+    let boolean = descriptor(None, &["true", "false"], Some("true"));
+    assert_eq!(Datatype::infer(&boolean), Datatype::Boolean);
+
+    // Allowable values win over a default that looks like a number
+    let choice = descriptor(None, &["1", "2", "5"], Some("1"));
+    assert_eq!(Datatype::infer(&choice), Datatype::String);
+}
+
+#[test]
+fn infers_from_default_value() {
+    let cases = [
+        (Some("25"), Datatype::Integer),
+        (Some("false"), Datatype::Boolean),
+        (Some("30 sec"), Datatype::TimePeriod),
+        (Some("1 GB"), Datatype::DataSize),
+        (Some("UTF-8"), Datatype::String),
+        (None, Datatype::String),
+    ];
+
+    for (default, expected) in cases {
+        let actual = Datatype::infer(&descriptor(None, &[], default));
+        assert_eq!(actual, expected, "{:?}", default);
+    }
+}
+
+#[test]
+fn normalizes_values() {
+    let cases = [
+        (Datatype::String, " keep me ", " keep me "),
+        (Datatype::Integer, " 42 ", "42"),
+        (Datatype::Integer, "-7", "-7"),
+        (Datatype::Boolean, "TRUE", "true"),
+        (Datatype::Boolean, "0", "false"),
+        (Datatype::TimePeriod, "30 sec", "30 secs"),
+        (Datatype::TimePeriod, "5mins", "5 mins"),
+        (Datatype::TimePeriod, "1.5 H", "1.5 hours"),
+        (Datatype::DataSize, "1gb", "1 GB"),
+        (Datatype::DataSize, "10 KB", "10 KB"),
+    ];
+
+    for (datatype, value, expected) in cases {
+        assert_eq!(datatype.normalize(value).as_deref(), Ok(expected));
+    }
+}
+
+#[test]
+fn rejects_invalid_values() {
+    let cases = [
+        (Datatype::Integer, "4.2"),
+        (Datatype::Integer, "POST"),
+        (Datatype::Boolean, "yes"),
+        (Datatype::TimePeriod, "30"),
+        (Datatype::TimePeriod, "30 fortnights"),
+        (Datatype::TimePeriod, "sec"),
+        (Datatype::DataSize, "1 PB"),
+        (Datatype::DataSize, "GB"),
+    ];
+
+    for (datatype, value) in cases {
+        assert!(
+            datatype.normalize(value).is_err(),
+            "{} accepted as {:?}",
+            value,
+            datatype
+        );
+    }
+}
+
+#[test]
+fn splits_amounts() {
+    assert_eq!(split_amount("30 sec"), Some(("30", "sec")));
+    assert_eq!(split_amount("1GB"), Some(("1", "GB")));
+    assert_eq!(split_amount("0.5  mins"), Some(("0.5", "mins")));
+
+    assert_eq!(split_amount("30"), None);
+    assert_eq!(split_amount("sec"), None);
+    assert_eq!(split_amount(".5 sec"), None);
+    assert_eq!(split_amount("5. sec"), None);
+    assert_eq!(split_amount("1.2.3 sec"), None);
+}
+
+#[test]
+fn iris_round_trip() {
+    for datatype in [
+        Datatype::String,
+        Datatype::Integer,
+        Datatype::Boolean,
+        Datatype::TimePeriod,
+        Datatype::DataSize,
+    ] {
+        assert_eq!(Datatype::from_iri(&datatype.iri()), Some(datatype));
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::{HashMap, HashSet};
+
+use oxigraph::model::{BlankNode, Literal, NamedNode};
+use serde::Serialize;
+
+use crate::client::Nifi;
+use crate::models::{ConnectableDTO, ConnectionDTO, ConnectionTargetType, ProcessorEntity};
+use crate::rdf::{iri, make_path_safe, prefix::*, type_iri, RdfContext, RdfGraph, ToRDF};
+
+/// Nifi returns this instead of the value of a sensitive property
+const SENSITIVE_VALUE: &str = "********";
+
+#[derive(Debug, Serialize)]
+pub struct ExportedLink {
+    pub source: String,
+    pub relationship: String,
+    pub target: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportedFlow {
+    pub processors: Vec<ProcessorEntity>,
+    pub links: Vec<ExportedLink>,
+}
+
+/// Reads the process group of the client, and all nested process groups, as one flat flow
+pub async fn export_flow(client: &Nifi) -> feignhttp::Result<ExportedFlow> {
+    let mut processors = Vec::new();
+    let mut connections = Vec::new();
+
+    let mut groups = vec![client.group.clone()];
+    while let Some(group) = groups.pop() {
+        eprintln!("Exporting process group {}", group);
+        let flow = client
+            .change_group(&group)
+            .get_flow()
+            .await?
+            .group_flow
+            .flow;
+
+        groups.extend(flow.process_groups.into_iter().map(|x| x.id));
+        processors.extend(flow.processors);
+        connections.extend(flow.connections.into_iter().map(|x| x.component));
+    }
+
+    // Nifi does not guarantee an order, sort to get the same output for the same flow
+    processors
+        .sort_by(|a, b| (&a.component.comp.name, &a.id).cmp(&(&b.component.comp.name, &b.id)));
+
+    let mut links = resolve_links(&connections);
+    links.sort_by(|a, b| {
+        (&a.source, &a.relationship, &a.target).cmp(&(&b.source, &b.relationship, &b.target))
+    });
+
+    Ok(ExportedFlow { processors, links })
+}
+
+/// Ports and funnels only pass flowfiles along, so connections are followed through them
+/// until they reach a processor.
+fn resolve_links(connections: &[ConnectionDTO]) -> Vec<ExportedLink> {
+    let mut outgoing: HashMap<&str, Vec<&ConnectionDTO>> = HashMap::new();
+    for con in connections {
+        outgoing
+            .entry(con.source.id.as_str())
+            .or_default()
+            .push(con);
+    }
+
+    let mut seen = HashSet::new();
+    let mut links = Vec::new();
+
+    for con in connections
+        .iter()
+        .filter(|x| x.source.ty == ConnectionTargetType::Processor)
+    {
+        let mut visited = HashSet::new();
+        let mut targets = Vec::new();
+        collect_targets(&con.destination, &outgoing, &mut visited, &mut targets);
+
+        for rel in &con.selected_relationships {
+            for target in &targets {
+                if seen.insert((con.source.id.as_str(), rel.as_str(), *target)) {
+                    links.push(ExportedLink {
+                        source: con.source.id.clone(),
+                        relationship: rel.clone(),
+                        target: target.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    links
+}
+
+fn collect_targets<'a>(
+    node: &'a ConnectableDTO,
+    outgoing: &HashMap<&'a str, Vec<&'a ConnectionDTO>>,
+    visited: &mut HashSet<&'a str>,
+    out: &mut Vec<&'a str>,
+) {
+    if !visited.insert(node.id.as_str()) {
+        return;
+    }
+
+    match node.ty {
+        ConnectionTargetType::Processor => out.push(node.id.as_str()),
+        ConnectionTargetType::RemoteInputPort | ConnectionTargetType::RemoteOutputPort => {
+            eprintln!("Skipping connection to remote port {}", node.id);
+        }
+        _ => {
+            for con in outgoing.get(node.id.as_str()).into_iter().flatten() {
+                collect_targets(&con.destination, outgoing, visited, out);
+            }
+        }
+    }
+}
+
+impl ToRDF for &ExportedFlow {
+    fn add_ctx(ctx: &mut RdfContext) {
+        ctx.add_prefix(&NIFI);
+        ctx.add_prefix(&CONN);
+        ctx.add_prefix(&RDF);
+        ctx.add_prefix(&RDFS);
+    }
+
+    fn to_rdf(self, graph: &mut RdfGraph) {
+        let nodes: HashMap<&str, BlankNode> = self
+            .processors
+            .iter()
+            .map(|x| (x.id.as_str(), graph.blank_node()))
+            .collect();
+
+        for proc in &self.processors {
+            let comp = &proc.component.comp;
+            let node = nodes[proc.id.as_str()].clone();
+
+            graph.add(
+                node.clone(),
+                iri(&RDF, "type"),
+                NamedNode::new_unchecked(type_iri(&comp.ty)),
+            );
+            graph.add(
+                node.clone(),
+                iri(&RDFS, "label"),
+                Literal::new_simple_literal(&comp.name),
+            );
+
+            if let Some(ref bundle) = comp.bundle {
+                graph.add(
+                    node.clone(),
+                    iri(&NIFI, "bundleVersion"),
+                    Literal::new_simple_literal(&bundle.version),
+                );
+            }
+
+            for (key, value) in &comp.config.properties {
+                let value = match value {
+                    Some(x) => x,
+                    None => continue,
+                };
+
+                let default = comp
+                    .config
+                    .descriptors
+                    .get(key)
+                    .and_then(|x| x.default_value.as_ref());
+                if default == Some(value) {
+                    continue;
+                }
+
+                if value == SENSITIVE_VALUE {
+                    eprintln!("Skipping sensitive property {} of {}", key, comp.name);
+                    continue;
+                }
+
+                graph.add(
+                    node.clone(),
+                    iri(&NIFI, &make_path_safe(key)),
+                    Literal::new_simple_literal(value),
+                );
+            }
+        }
+
+        for link in &self.links {
+            let (source, target) = match (
+                nodes.get(link.source.as_str()),
+                nodes.get(link.target.as_str()),
+            ) {
+                (Some(s), Some(t)) => (s.clone(), t.clone()),
+                _ => continue,
+            };
+
+            let writer = graph.blank_node();
+            let reader = graph.blank_node();
+            let channel = graph.blank_node();
+
+            graph.add(
+                source,
+                iri(&NIFI, &make_path_safe(&link.relationship)),
+                writer.clone(),
+            );
+            graph.add(target, iri(&NIFI, "INCOMING_CHANNEL"), reader.clone());
+            graph.add(
+                channel.clone(),
+                iri(&RDF, "type"),
+                iri(&NIFI, "NifiChannel"),
+            );
+            graph.add(channel.clone(), iri(&CONN, "reader"), reader);
+            graph.add(channel, iri(&CONN, "writer"), writer);
+        }
+    }
+}
@@ -0,0 +1,13 @@
+#![feature(adt_const_params)]
+
+pub mod app;
+pub mod cache;
+pub mod channels;
+pub mod client;
+pub mod datatype;
+pub mod export;
+pub mod logic;
+pub mod models;
+pub mod rdf;
+mod sparql;
+mod util;
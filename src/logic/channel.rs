@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use oxigraph::model::{Literal, NamedNodeRef, QuadRef, Term};
+use oxigraph::store::Store;
+
+use super::nodes::ChannelNodeOutput;
+use super::{as_subject_ref, configure_channel, template_file_id, ChannelRegistry};
+use super::{ID_TERM, RUNTIME_GRAPH};
+use crate::client::{Nifi, PortType};
+use crate::models::{Component, PortDTO};
+
+#[cfg(test)]
+mod tests;
+
+/// Whether the channel feeds the processors or the processors write to it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelSide {
+    Reader,
+    Writer,
+}
+
+impl ChannelSide {
+    /// Type of the port of a channel group that the processors are linked to
+    pub fn port_type(self) -> PortType {
+        match self {
+            ChannelSide::Reader => PortType::Output,
+            ChannelSide::Writer => PortType::Input,
+        }
+    }
+
+    /// IRI of the class of the nodes on this side
+    pub fn class(self) -> &'static str {
+        match self {
+            ChannelSide::Reader => "https://w3id.org/conn#ReaderChannel",
+            ChannelSide::Writer => "https://w3id.org/conn#WriterChannel",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChannelSide::Reader => "reader",
+            ChannelSide::Writer => "writer",
+        }
+    }
+}
+
+/// A reader or writer node of the input
+pub struct ChannelConfig {
+    pub node: Term,
+    /// IRI of the channel type of the node
+    pub ty: String,
+    pub side: ChannelSide,
+    /// Values of the node, by `fnom:implementationParameterPosition`
+    pub values: HashMap<String, String>,
+}
+
+/// The config of a reader or writer node from the rows of its values
+pub(super) fn channel_config(
+    sol: &[ChannelNodeOutput],
+    side: ChannelSide,
+) -> Option<ChannelConfig> {
+    let ty = match &sol[0].channel_type.0 {
+        Term::NamedNode(n) => n.as_str().to_string(),
+        _ => {
+            eprintln!("Expected a named node as {} type", side.name());
+            return None;
+        }
+    };
+
+    let values = sol
+        .iter()
+        .map(|sol| (sol.nifi_key.0.clone(), sol.value.0.clone()))
+        .collect();
+
+    Some(ChannelConfig {
+        node: sol[0].subject.0.clone(),
+        ty,
+        side,
+        values,
+    })
+}
+
+/// Where channels are created
+pub struct ChannelContext<'a> {
+    /// Client of the process group of the pipeline
+    pub client: &'a Nifi,
+    pub registry: &'a ChannelRegistry,
+    /// Uploaded templates by channel type
+    templates: HashMap<String, String>,
+}
+
+impl<'a> ChannelContext<'a> {
+    pub fn new(client: &'a Nifi, registry: &'a ChannelRegistry) -> Self {
+        Self {
+            client,
+            registry,
+            templates: HashMap::new(),
+        }
+    }
+
+    /// Id of the uploaded `nifi:template` of a channel type, uploaded once
+    pub async fn template(&mut self, ty: &str) -> Option<String> {
+        if let Some(id) = self.templates.get(ty) {
+            return Some(id.clone());
+        }
+
+        let channel = match self.registry.get(ty) {
+            Some(x) => x,
+            None => {
+                eprintln!("No nifi:template found for {}", ty);
+                return None;
+            }
+        };
+
+        println!("Uploading new template");
+        let id = template_file_id(self.client, &channel.template).await?;
+        self.templates.insert(ty.to_string(), id.clone());
+        Some(id)
+    }
+
+    /// Deletes the uploaded templates
+    pub async fn finish(self) {
+        for id in self.templates.into_values() {
+            if let Err(e) = self.client.delete_template(&id).await {
+                eprintln!("Failed to delete template {:?}", e.error_kind());
+            }
+        }
+    }
+}
+
+/// The nifi side of a channel
+pub struct ChannelEndpoint {
+    /// Port that is linked to the processors
+    pub port: Component<PortDTO>,
+    pub start: ChannelStart,
+}
+
+/// What is started once a channel is linked
+pub enum ChannelStart {
+    /// The process group of the channel
+    Group(Nifi),
+    /// A port of the process group of the pipeline
+    Port(Nifi, PortType, String),
+}
+
+impl ChannelStart {
+    pub async fn start(&self) -> feignhttp::Result<()> {
+        match self {
+            ChannelStart::Group(client) => client.start_process_group().await,
+            ChannelStart::Port(client, ty, id) => client.start_port(*ty, id).await,
+        }
+    }
+}
+
+/// A kind of channel, like websockets or kafka
+#[async_trait::async_trait(?Send)]
+pub trait Channel {
+    /// Name and turtle content of the ontology that declares the channel types, a file with the
+    /// same name in a channel directory replaces it
+    fn ontology(&self) -> Option<(&str, &str)> {
+        None
+    }
+
+    /// IRIs of the channel types this plugin creates
+    fn types(&self) -> &[&str];
+
+    /// Creates the nifi side of a reader or writer in the process group of the context
+    async fn create(
+        &self,
+        ctx: &mut ChannelContext<'_>,
+        config: &ChannelConfig,
+    ) -> Option<ChannelEndpoint>;
+}
+
+/// Channels that are a nifi template, declared with `nifi:template`
+#[derive(Clone, Copy)]
+pub struct TemplateChannel {
+    pub ontology: Option<(&'static str, &'static str)>,
+    pub types: &'static [&'static str],
+}
+
+#[async_trait::async_trait(?Send)]
+impl Channel for TemplateChannel {
+    fn ontology(&self) -> Option<(&str, &str)> {
+        self.ontology
+    }
+
+    fn types(&self) -> &[&str] {
+        self.types
+    }
+
+    async fn create(
+        &self,
+        ctx: &mut ChannelContext<'_>,
+        config: &ChannelConfig,
+    ) -> Option<ChannelEndpoint> {
+        let template_id = ctx.template(&config.ty).await?;
+        let channel = ctx.registry.get(&config.ty)?;
+
+        let flow = match ctx.client.instantiate_template(&template_id).await {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("Failed to instantiate template\n{:?}", e);
+                return None;
+            }
+        };
+
+        let group_client = ctx.client.change_group(&flow.flow.process_groups[0].id);
+        let ports = group_client
+            .get_ports(config.side.port_type())
+            .await
+            .ok()?
+            .ports;
+        let port = channel.find_port(&ports)?.component.clone();
+
+        let values = config.values.clone().into_iter();
+        configure_channel(&group_client, values).await?;
+
+        Some(ChannelEndpoint {
+            port,
+            start: ChannelStart::Group(group_client),
+        })
+    }
+}
+
+/// The channel plugins, by default the stock channels
+pub struct ChannelPlugins {
+    plugins: Vec<Box<dyn Channel>>,
+    /// Creates the channel types with a `nifi:template` that no plugin claims
+    fallback: TemplateChannel,
+}
+
+impl ChannelPlugins {
+    /// Registers a plugin, it takes precedence over the plugins registered before
+    pub fn register(&mut self, plugin: impl Channel + 'static) {
+        self.plugins.insert(0, Box::new(plugin));
+    }
+
+    pub fn ontologies(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.plugins.iter().flat_map(|x| x.ontology())
+    }
+
+    fn find(&self, ty: &str, registry: &ChannelRegistry) -> Option<&dyn Channel> {
+        if let Some(plugin) = self.plugins.iter().find(|x| x.types().contains(&ty)) {
+            return Some(plugin.as_ref());
+        }
+
+        registry.get(ty).map(|_| &self.fallback as &dyn Channel)
+    }
+
+    /// Creates the channel of a node with the plugin of its type and records the id of its port
+    pub(crate) async fn create(
+        &self,
+        ctx: &mut ChannelContext<'_>,
+        store: &Store,
+        config: ChannelConfig,
+        ports: &mut HashMap<String, Component<PortDTO>>,
+    ) -> Option<ChannelStart> {
+        let plugin = match self.find(&config.ty, ctx.registry) {
+            Some(x) => x,
+            None => {
+                eprintln!("No channel plugin found for {}", config.ty);
+                return None;
+            }
+        };
+
+        let endpoint = plugin.create(ctx, &config).await?;
+
+        let v = Literal::new_simple_literal(&endpoint.port.id);
+        store
+            .insert(QuadRef {
+                subject: as_subject_ref(config.node.as_ref()),
+                predicate: NamedNodeRef::new(ID_TERM).unwrap().into(),
+                object: v.as_ref().into(),
+                graph_name: NamedNodeRef::new(RUNTIME_GRAPH).unwrap().into(),
+            })
+            .unwrap();
+        ports.insert(endpoint.port.id.clone(), endpoint.port);
+
+        Some(endpoint.start)
+    }
+}
+
+impl Default for ChannelPlugins {
+    fn default() -> Self {
+        let mut plugins: Vec<Box<dyn Channel>> = Vec::new();
+        for channel in crate::channels::TEMPLATE_CHANNELS {
+            plugins.push(Box::new(*channel));
+        }
+        plugins.push(Box::new(super::site_to_site::SiteToSiteChannel));
+        plugins.push(Box::new(super::port::PortChannel));
+
+        Self {
+            plugins,
+            fallback: TemplateChannel {
+                ontology: None,
+                types: &[],
+            },
+        }
+    }
+}
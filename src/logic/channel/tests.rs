@@ -0,0 +1,91 @@
+use oxigraph::io::GraphFormat;
+use oxigraph::model::{GraphName, GraphNameRef, NamedNode, NamedNodeRef};
+use oxigraph::store::Store;
+
+use super::{Channel, ChannelConfig, ChannelContext, ChannelEndpoint, ChannelPlugins};
+use crate::logic::ChannelRegistry;
+use crate::sparql::Dataset;
+
+const INPUT_GRAPH: &str = "http://example.com/input";
+const WS_READER: &str = "https://w3id.org/conn#WsReaderChannel";
+const CUSTOM: &str = "http://example.com/CustomChannel";
+const UNKNOWN: &str = "http://example.com/UnknownChannel";
+
+/// A plugin that is recognised by its ontology name
+struct Dummy(&'static str, &'static [&'static str]);
+
+#[async_trait::async_trait(?Send)]
+impl Channel for Dummy {
+    fn ontology(&self) -> Option<(&str, &str)> {
+        Some((self.0, ""))
+    }
+
+    fn types(&self) -> &[&str] {
+        self.1
+    }
+
+    async fn create(
+        &self,
+        _: &mut ChannelContext<'_>,
+        _: &ChannelConfig,
+    ) -> Option<ChannelEndpoint> {
+        None
+    }
+}
+
+/// A registry with a `nifi:template` for the custom channel type
+fn registry() -> ChannelRegistry {
+    let store = Store::new().unwrap();
+    let input = format!(
+        "<{}> <https://w3id.org/conn/nifi#template> <file:///channels/Custom.xml>.",
+        CUSTOM
+    );
+    store
+        .load_graph(
+            input.as_bytes(),
+            GraphFormat::Turtle,
+            GraphNameRef::NamedNode(NamedNodeRef::new(INPUT_GRAPH).unwrap()),
+            None,
+        )
+        .unwrap();
+
+    let dataset = Dataset {
+        store: &store,
+        graphs: vec![GraphName::NamedNode(NamedNode::new(INPUT_GRAPH).unwrap())],
+    };
+    ChannelRegistry::load(&dataset)
+}
+
+/// Ontology name of the plugin that creates the type
+fn found(plugins: &ChannelPlugins, ty: &str) -> Option<String> {
+    let plugin = plugins.find(ty, &registry())?;
+    Some(plugin.ontology().map_or("fallback", |x| x.0).to_string())
+}
+
+#[test]
+fn builtin_plugins_create_their_types() {
+    let plugins = ChannelPlugins::default();
+
+    assert_eq!(
+        found(&plugins, WS_READER).as_deref(),
+        Some("ws_ontology.ttl")
+    );
+}
+
+#[test]
+fn registered_plugins_take_precedence() {
+    let mut plugins = ChannelPlugins::default();
+    plugins.register(Dummy("first", &[WS_READER, CUSTOM]));
+    plugins.register(Dummy("second", &[CUSTOM]));
+
+    assert_eq!(found(&plugins, WS_READER).as_deref(), Some("first"));
+    assert_eq!(found(&plugins, CUSTOM).as_deref(), Some("second"));
+}
+
+#[test]
+fn templates_without_plugin_use_the_fallback() {
+    let plugins = ChannelPlugins::default();
+
+    assert_eq!(found(&plugins, CUSTOM).as_deref(), Some("fallback"));
+    assert_eq!(found(&plugins, UNKNOWN), None);
+}
@@ -0,0 +1,577 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use oxigraph::model::{GraphName, NamedNode, Quad, Term};
+use oxigraph::store::Store;
+use oxiri::Iri;
+use rio_api::parser::{LineBytePosition, QuadsParser, TriplesParser};
+use rio_turtle::{NQuadsParser, NTriplesParser, TriGParser, TurtleParser};
+use rio_xml::RdfXmlParser;
+
+use super::ONTOLOGY_GRAPH;
+use crate::app::InputFormat;
+use crate::rdf::read_jsonld;
+use crate::util::*;
+
+#[cfg(test)]
+mod tests;
+
+const OWL_IMPORTS: &str = "http://www.w3.org/2002/07/owl#imports";
+
+/// Extensions of the files loaded from a directory, `.json` and `.xml` files next to the inputs
+/// are rarely RDF so they have to be given explicitly
+const DIRECTORY_EXTENSIONS: &[&str] = &["ttl", "nt", "nq", "trig", "jsonld", "rdf", "owl"];
+
+/// How input files are parsed
+#[derive(Default)]
+pub struct ParseOptions {
+    /// Taken from the file extension when not set, defaulting to turtle
+    pub format: Option<InputFormat>,
+    /// Base IRI to resolve relative IRIs against
+    pub base: Option<String>,
+    /// Report all parse errors instead of stopping at the first one
+    pub keep_going: bool,
+}
+
+/// Where and how the pipeline description is read
+pub struct InputOptions {
+    /// Files and directories to read, stdin when empty
+    pub locations: Vec<String>,
+    pub parse: ParseOptions,
+}
+
+/// An error in an input file, positions start at 1
+pub struct ParseError {
+    pub source: String,
+    pub position: Option<(u64, u64)>,
+    pub message: String,
+    pub snippet: Option<String>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some((line, column)) => {
+                write!(f, "{}:{}:{}: {}", self.source, line, column, self.message)?
+            }
+            None => write!(f, "{}: {}", self.source, self.message)?,
+        }
+
+        if let Some(ref snippet) = self.snippet {
+            write!(f, "\n    | {}", snippet)?;
+            if let Some((_, column)) = self.position {
+                let offset = " ".repeat(column.saturating_sub(1) as usize);
+                write!(f, "\n    | {}^", offset)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The parse errors that stopped a run
+pub struct ParseErrors(pub Vec<ParseError>);
+
+impl ParseErrors {
+    pub fn io(source: &str, error: io::Error) -> Self {
+        ParseErrors(vec![ParseError {
+            source: source.to_string(),
+            position: None,
+            message: error.to_string(),
+            snippet: None,
+        }])
+    }
+}
+
+impl fmt::Display for ParseErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for error in &self.0 {
+            writeln!(f, "{}", error)?;
+        }
+        write!(f, "{} parse error(s)", self.0.len())
+    }
+}
+
+// main reports errors with their debug representation
+impl fmt::Debug for ParseErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for ParseErrors {}
+
+impl From<ParseErrors> for io::Error {
+    fn from(errors: ParseErrors) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, errors.to_string())
+    }
+}
+
+/// Content of an input, used to point at the location of an error
+struct Source<'a> {
+    name: &'a str,
+    content: &'a [u8],
+}
+
+impl<'a> Source<'a> {
+    /// Error at a zero based line and byte in that line
+    fn error(&self, message: impl ToString, position: Option<(u64, u64)>) -> ParseError {
+        let line = position.and_then(|(line, _)| {
+            self.content
+                .split(|x| *x == b'\n')
+                .nth(line as usize)
+                .map(String::from_utf8_lossy)
+        });
+
+        let position = position.map(|(line_number, byte)| {
+            let column = line
+                .as_ref()
+                .and_then(|x| x.get(..byte as usize))
+                .map(|x| x.chars().count() as u64)
+                .unwrap_or(byte);
+            (line_number + 1, column + 1)
+        });
+
+        ParseError {
+            source: self.name.to_string(),
+            position,
+            message: message.to_string(),
+            snippet: line.map(|x| x.trim_end().to_string()),
+        }
+    }
+
+    fn rio_error<E: rio_api::parser::ParseError>(&self, error: &E) -> ParseError {
+        let position = error
+            .textual_position()
+            .map(|x: LineBytePosition| (x.line_number(), x.byte_number()));
+        self.error(error, position)
+    }
+}
+
+/// Where the statements of the loaded files end up
+#[derive(Clone, Copy)]
+enum Target<'a> {
+    /// Each file in a graph named after its file IRI, named graphs of quad formats are kept
+    Documents,
+    /// Everything in the given graph
+    Graph(&'a str),
+}
+
+/// Inserts the statements of one document, and remembers the IRIs it imports
+struct Sink<'a> {
+    store: &'a Store,
+    graph: GraphName,
+    keep_named: bool,
+    imports: Vec<String>,
+}
+
+impl<'a> Sink<'a> {
+    fn new(store: &'a Store, target: Target, document: &str) -> Self {
+        let (graph, keep_named) = match target {
+            Target::Documents => (document, true),
+            Target::Graph(graph) => (graph, false),
+        };
+
+        Sink {
+            store,
+            graph: NamedNode::new_unchecked(graph).into(),
+            keep_named,
+            imports: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, quad: Quad) {
+        if let (OWL_IMPORTS, Term::NamedNode(import)) = (quad.predicate.as_str(), &quad.object) {
+            self.imports.push(import.as_str().to_string());
+        }
+        self.store.insert(&quad).unwrap();
+    }
+}
+
+fn parse_triples<P: TriplesParser>(
+    mut parser: P,
+    source: &Source,
+    keep_going: bool,
+    sink: &mut Sink,
+) -> Vec<ParseError>
+where
+    P::Error: rio_api::parser::ParseError,
+{
+    let mut mapper = RDFMapper::default();
+    let mut errors: Vec<ParseError> = Vec::new();
+
+    while !parser.is_end() {
+        let result = parser.parse_step(&mut |triple| -> Result<(), P::Error> {
+            sink.insert(mapper.map_triple_to_quad(triple, sink.graph.clone()));
+            Ok(())
+        });
+
+        if let Err(e) = result {
+            let error = source.rio_error(&e);
+            // A parser that can't recover keeps failing at the same position
+            let stuck = errors
+                .last()
+                .map_or(false, |x| x.position == error.position);
+            if !stuck {
+                errors.push(error);
+            }
+            if !keep_going || stuck {
+                break;
+            }
+        }
+    }
+
+    errors
+}
+
+/// Quads in the default graph end up in the graph of the document
+fn parse_quads<P: QuadsParser>(
+    mut parser: P,
+    source: &Source,
+    keep_going: bool,
+    sink: &mut Sink,
+) -> Vec<ParseError>
+where
+    P::Error: rio_api::parser::ParseError,
+{
+    let mut mapper = RDFMapper::default();
+    let mut errors: Vec<ParseError> = Vec::new();
+
+    while !parser.is_end() {
+        let result = parser.parse_step(&mut |quad| -> Result<(), P::Error> {
+            let graph = match quad.graph_name {
+                Some(ref graph) if sink.keep_named => mapper.map_graph_name(graph),
+                _ => sink.graph.clone(),
+            };
+            let triple = rio_api::model::Triple {
+                subject: quad.subject,
+                predicate: quad.predicate,
+                object: quad.object,
+            };
+            sink.insert(mapper.map_triple_to_quad(triple, graph));
+            Ok(())
+        });
+
+        if let Err(e) = result {
+            let error = source.rio_error(&e);
+            let stuck = errors
+                .last()
+                .map_or(false, |x| x.position == error.position);
+            if !stuck {
+                errors.push(error);
+            }
+            if !keep_going || stuck {
+                break;
+            }
+        }
+    }
+
+    errors
+}
+
+fn parse_jsonld(source: &Source, base: Option<Iri<String>>, sink: &mut Sink) -> Vec<ParseError> {
+    match read_jsonld(source.content, base) {
+        Ok(triples) => {
+            for triple in triples {
+                let graph = sink.graph.clone();
+                sink.insert(Quad::new(
+                    triple.subject,
+                    triple.predicate,
+                    triple.object,
+                    graph,
+                ));
+            }
+            Vec::new()
+        }
+        Err(e) => {
+            let position = e
+                .get_ref()
+                .and_then(|x| x.downcast_ref::<serde_json::Error>())
+                .filter(|x| x.line() > 0)
+                .map(|x| (x.line() as u64 - 1, x.column().saturating_sub(1) as u64));
+            vec![source.error(e, position)]
+        }
+    }
+}
+
+/// Parses everything in the reader into the sink, and returns the IRIs it imports
+fn import_content<R: Read>(
+    mut reader: R,
+    name: &str,
+    format: InputFormat,
+    base: Option<&str>,
+    keep_going: bool,
+    mut sink: Sink,
+) -> Result<Vec<String>, ParseErrors> {
+    let mut content = Vec::new();
+    reader
+        .read_to_end(&mut content)
+        .map_err(|e| ParseErrors::io(name, e))?;
+
+    let base = base
+        .map(|x| Iri::parse(x.to_string()))
+        .transpose()
+        .map_err(|e| {
+            ParseErrors::io(
+                name,
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid base IRI: {}", e),
+                ),
+            )
+        })?;
+
+    let source = Source {
+        name,
+        content: &content,
+    };
+    let input = content.as_slice();
+    let sk = &mut sink;
+
+    let errors = match format {
+        InputFormat::Turtle => {
+            parse_triples(TurtleParser::new(input, base), &source, keep_going, sk)
+        }
+        InputFormat::NTriples => parse_triples(NTriplesParser::new(input), &source, keep_going, sk),
+        InputFormat::NQuads => parse_quads(NQuadsParser::new(input), &source, keep_going, sk),
+        InputFormat::Trig => parse_quads(TriGParser::new(input, base), &source, keep_going, sk),
+        InputFormat::RdfXml => {
+            parse_triples(RdfXmlParser::new(input, base), &source, keep_going, sk)
+        }
+        InputFormat::JsonLd => parse_jsonld(&source, base, sk),
+    };
+
+    if errors.is_empty() {
+        Ok(sink.imports)
+    } else {
+        Err(ParseErrors(errors))
+    }
+}
+
+/// `file:` IRI of a path, relative IRIs in a file are resolved against it when there is no base
+fn file_iri(path: &Path) -> String {
+    let mut out = String::from("file://");
+    for b in path.to_string_lossy().bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~/".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|x| std::str::from_utf8(x).ok())
+            .and_then(|x| u8::from_str_radix(x, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(x)) => {
+                out.push(x);
+                i += 3;
+            }
+            (x, _) => {
+                out.push(x);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Local path of a `file:` IRI
+pub fn file_path(iri: &str) -> Option<PathBuf> {
+    iri.strip_prefix("file://")
+        .map(|path| PathBuf::from(percent_decode(path)))
+}
+
+/// Local file an `owl:imports` IRI refers to, relative IRIs resolved against `--base` are taken
+/// relative to the importing document
+fn import_path(iri: &str, importer: &Path, base: Option<&str>) -> Option<PathBuf> {
+    if let Some(path) = file_path(iri) {
+        return Some(path);
+    }
+
+    let base = base?;
+    let base_dir = &base[..=base.rfind('/')?];
+    let relative = iri.strip_prefix(base_dir)?;
+
+    let dir = importer.parent().unwrap_or_else(|| Path::new("."));
+    Some(dir.join(percent_decode(relative)))
+}
+
+/// Files of a directory, recursively and sorted, that have one of the extensions
+pub fn directory_files(dir: &Path, extensions: &[&str], out: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?
+        .map(|x| x.map(|x| x.path()))
+        .collect::<io::Result<_>>()?;
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() {
+            directory_files(&entry, extensions, out)?;
+        } else if has_extension(&entry, extensions) {
+            out.push(entry);
+        }
+    }
+
+    Ok(())
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|x| x.to_str())
+        .map_or(false, |x| extensions.contains(&x.to_lowercase().as_str()))
+}
+
+/// Imports the files, and the local files they import with `owl:imports`, each file at most once.
+/// Returns the IRIs of the loaded documents
+fn import_files(
+    mut queue: VecDeque<(PathBuf, Option<InputFormat>)>,
+    options: &ParseOptions,
+    target: Target,
+    bl: &Store,
+) -> Result<Vec<String>, ParseErrors> {
+    let mut loaded = HashSet::new();
+    let mut documents = Vec::new();
+    let mut errors = Vec::new();
+
+    while let Some((path, format)) = queue.pop_front() {
+        let path = fs::canonicalize(&path).unwrap_or(path);
+        if !loaded.insert(path.clone()) {
+            eprintln!("Skipping {}, it is already loaded", path.display());
+            continue;
+        }
+
+        println!("Loading file {}", path.display());
+        let name = path.display().to_string();
+        let format = format
+            .or_else(|| InputFormat::from_path(&path))
+            .unwrap_or(InputFormat::Turtle);
+        let document = file_iri(&path);
+        let base = options.base.clone().unwrap_or_else(|| document.clone());
+        let sink = Sink::new(bl, target, &document);
+        documents.push(document);
+
+        let imported = File::open(&path)
+            .map_err(|e| ParseErrors::io(&name, e))
+            .and_then(|file| {
+                import_content(file, &name, format, Some(&base), options.keep_going, sink)
+            });
+
+        match imported {
+            Ok(imports) => {
+                for iri in imports {
+                    match import_path(&iri, &path, options.base.as_deref()) {
+                        Some(import) => queue.push_back((import, None)),
+                        None => eprintln!("Skipping import of {}, it is not a local file", iri),
+                    }
+                }
+            }
+            Err(e) if !options.keep_going => return Err(e),
+            Err(ParseErrors(e)) => errors.extend(e),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(documents)
+    } else {
+        Err(ParseErrors(errors))
+    }
+}
+
+/// Imports the given files and directories each in their own graph, `--input-format` only
+/// applies to the given files. Returns the IRIs of the document graphs
+pub fn import_inputs(
+    locations: &[String],
+    options: &ParseOptions,
+    bl: &Store,
+) -> Result<Vec<String>, ParseErrors> {
+    let mut queue = VecDeque::new();
+
+    for location in locations {
+        let path = PathBuf::from(location);
+        if path.is_dir() {
+            let mut files = Vec::new();
+            directory_files(&path, DIRECTORY_EXTENSIONS, &mut files)
+                .map_err(|e| ParseErrors::io(location, e))?;
+            queue.extend(files.into_iter().map(|x| (x, None)));
+        } else {
+            queue.push_back((path, options.format));
+        }
+    }
+
+    import_files(queue, options, Target::Documents, bl)
+}
+
+/// Imports stdin like [`import_inputs`], imported files are taken relative to the current
+/// directory
+pub fn import_stdin(options: &ParseOptions, bl: &Store) -> Result<Vec<String>, ParseErrors> {
+    let format = options.format.unwrap_or(InputFormat::Turtle);
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let document = file_iri(&cwd.join("stdin"));
+    let base = options.base.clone().unwrap_or_else(|| document.clone());
+
+    let imports = import_content(
+        io::stdin().lock(),
+        "<stdin>",
+        format,
+        Some(&base),
+        options.keep_going,
+        Sink::new(bl, Target::Documents, &document),
+    )?;
+
+    let queue = imports
+        .into_iter()
+        .filter_map(|iri| {
+            let path = import_path(&iri, &cwd.join("stdin"), options.base.as_deref());
+            if path.is_none() {
+                eprintln!("Skipping import of {}, it is not a local file", iri);
+            }
+            path.map(|x| (x, None))
+        })
+        .collect();
+
+    let mut documents = import_files(queue, options, Target::Documents, bl)?;
+    documents.insert(0, document);
+    Ok(documents)
+}
+
+/// Imports an ontology into the ontology graph, the format is taken from its extension when not
+/// given
+pub fn import_ontology<P: AsRef<Path>>(
+    location: P,
+    options: &ParseOptions,
+    bl: &Store,
+) -> Result<(), ParseErrors> {
+    let queue = VecDeque::from([(location.as_ref().to_path_buf(), options.format)]);
+    import_files(queue, options, Target::Graph(ONTOLOGY_GRAPH), bl)?;
+    Ok(())
+}
+
+/// Imports turtle into the ontology graph, relative IRIs are resolved against `base`
+pub fn import_ontology_content(
+    content: &[u8],
+    name: &str,
+    base: &str,
+    bl: &Store,
+) -> Result<(), ParseErrors> {
+    let sink = Sink::new(bl, Target::Graph(ONTOLOGY_GRAPH), base);
+    import_content(content, name, InputFormat::Turtle, Some(base), false, sink)?;
+    Ok(())
+}
+
+pub fn import_ontology_file<P: AsRef<Path>>(location: P, bl: &Store) -> io::Result<()> {
+    Ok(import_ontology(location, &ParseOptions::default(), bl)?)
+}
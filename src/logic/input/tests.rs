@@ -0,0 +1,143 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use oxigraph::model::{GraphNameRef, NamedNodeRef};
+use oxigraph::store::Store;
+
+use super::{
+    directory_files, file_iri, file_path, import_inputs, import_path, percent_decode, ParseOptions,
+    DIRECTORY_EXTENSIONS,
+};
+
+/// An empty directory for one test
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("nifi-runner-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::canonicalize(dir).unwrap()
+}
+
+fn write(dir: &Path, name: &str, content: &str) -> PathBuf {
+    let path = dir.join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, content).unwrap();
+    path
+}
+
+fn statements(store: &Store, document: &str) -> usize {
+    let graph = GraphNameRef::NamedNode(NamedNodeRef::new(document).unwrap());
+    store
+        .quads_for_pattern(None, None, None, Some(graph))
+        .count()
+}
+
+#[test]
+fn directories_only_load_rdf_files() {
+    let dir = temp_dir("directory");
+    for name in [
+        "a.ttl",
+        "b.json",
+        "c.xml",
+        "d.OWL",
+        "e.txt",
+        "nested/f.nt",
+        "nested/g.jsonld",
+    ] {
+        write(&dir, name, "");
+    }
+
+    let mut files = Vec::new();
+    directory_files(&dir, DIRECTORY_EXTENSIONS, &mut files).unwrap();
+
+    let expected: Vec<PathBuf> = ["a.ttl", "d.OWL", "nested/f.nt", "nested/g.jsonld"]
+        .iter()
+        .map(|x| dir.join(x))
+        .collect();
+    assert_eq!(files, expected);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn imports_are_loaded_once() {
+    let dir = temp_dir("imports");
+    let prefixes = "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n";
+
+    let main = write(
+        &dir,
+        "main.ttl",
+        &format!("{}<> owl:imports <second.ttl>; a owl:Ontology.", prefixes),
+    );
+    let second = write(
+        &dir,
+        "second.ttl",
+        &format!("{}<> owl:imports <third/third.ttl>.", prefixes),
+    );
+    // Imports the first file again
+    let third = write(
+        &dir,
+        "third/third.ttl",
+        &format!("{}<> owl:imports <../main.ttl>, <../second.ttl>.", prefixes),
+    );
+
+    let store = Store::new().unwrap();
+    let location = main.display().to_string();
+    let documents = import_inputs(
+        &[location.clone(), location],
+        &ParseOptions::default(),
+        &store,
+    )
+    .unwrap();
+
+    let expected: Vec<String> = [&main, &second, &third]
+        .iter()
+        .map(|x| file_iri(x))
+        .collect();
+    assert_eq!(documents, expected);
+
+    assert_eq!(statements(&store, &expected[0]), 2);
+    assert_eq!(statements(&store, &expected[1]), 1);
+    assert_eq!(statements(&store, &expected[2]), 2);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn imports_relative_to_the_base() {
+    let importer = Path::new("/data/pipeline/main.ttl");
+    let base = Some("http://example.com/pipeline/main.ttl");
+
+    assert_eq!(
+        import_path("http://example.com/pipeline/sub/x.ttl", importer, base),
+        Some(PathBuf::from("/data/pipeline/sub/x.ttl"))
+    );
+    assert_eq!(
+        import_path("file:///etc/x%20y.ttl", importer, base),
+        Some(PathBuf::from("/etc/x y.ttl"))
+    );
+    assert_eq!(import_path("http://other.org/x.ttl", importer, base), None);
+    assert_eq!(
+        import_path("http://example.com/pipeline/x.ttl", importer, None),
+        None
+    );
+}
+
+#[test]
+fn file_iris_round_trip() {
+    let path = Path::new("/tmp/my dir/é#1.ttl");
+    let iri = file_iri(path);
+
+    assert_eq!(iri, "file:///tmp/my%20dir/%C3%A9%231.ttl");
+    assert_eq!(file_path(&iri).as_deref(), Some(path));
+    assert_eq!(file_path("http://example.com/x.ttl"), None);
+}
+
+#[test]
+fn percent_decoding() {
+    assert_eq!(percent_decode("a%20b"), "a b");
+    assert_eq!(percent_decode("%C3%A9"), "é");
+    assert_eq!(percent_decode("%41%2f"), "A/");
+    // Invalid escapes are kept
+    assert_eq!(percent_decode("100%"), "100%");
+    assert_eq!(percent_decode("%zz%4"), "%zz%4");
+}
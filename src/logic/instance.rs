@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use derive::Query;
+use oxigraph::model::Term;
+
+use crate::client::Nifi;
+use crate::sparql::{execute_query, Dataset, QueryField, QueryString, Queryable, Sol};
+
+#[cfg(test)]
+mod tests;
+
+/// The nifi instances of a pipeline, the first one is the instance given on the command line.
+/// Other instances are declared as `nifi:Instance` with a `nifi:url` and optionally a `nifi:group`,
+/// `nifi:converter` and `nifi:credentials`, processors are assigned to them with `nifi:runsOn`
+pub struct Instances {
+    clients: Vec<Nifi>,
+    runs_on: HashMap<Term, usize>,
+}
+
+impl Instances {
+    pub fn load(dataset: &Dataset, default: &Nifi) -> Result<Self, String> {
+        let mut clients = vec![default.change_group(&default.group)];
+        let mut nodes = HashMap::new();
+
+        for instance in execute_query::<InstanceQuery>(dataset) {
+            if nodes.contains_key(&instance.instance.0) {
+                eprintln!("Instance {} has multiple urls", instance.instance.0);
+                continue;
+            }
+
+            let group = instance.group.as_ref().map_or("root", |x| x.as_str());
+            println!(
+                "Found instance {} at {}",
+                instance.instance.0, instance.url.0
+            );
+            nodes.insert(instance.instance.0, clients.len());
+            let converter = instance.converter.as_ref().map(|x| x.as_str());
+            let credentials = instance.credentials.as_ref().map(|x| x.as_str());
+            clients.push(Nifi::new(&instance.url, group, converter, credentials)?);
+        }
+
+        let mut runs_on = HashMap::new();
+        for assigned in execute_query::<RunsOnQuery>(dataset) {
+            match nodes.get(&assigned.instance.0) {
+                Some(index) => {
+                    runs_on.insert(assigned.subject.0, *index);
+                }
+                None => eprintln!(
+                    "Unknown instance {} for {}, using the default instance",
+                    assigned.instance.0, assigned.subject.0
+                ),
+            }
+        }
+
+        Ok(Self { clients, runs_on })
+    }
+
+    /// Index of the instance a processor runs on
+    pub fn runs_on(&self, subject: &Term) -> usize {
+        self.runs_on.get(subject).copied().unwrap_or_default()
+    }
+
+    pub fn client(&self, index: usize) -> &Nifi {
+        &self.clients[index]
+    }
+}
+
+#[derive(Debug, Query)]
+struct InstanceOutput {
+    pub instance: QueryField<Term, "instance">,
+    pub url: QueryString<"url">,
+    pub group: Option<QueryString<"group">>,
+    pub converter: Option<QueryString<"converter">>,
+    /// Environment variable with the access token
+    pub credentials: Option<QueryString<"credentials">>,
+}
+
+struct InstanceQuery;
+impl Queryable for InstanceQuery {
+    const ERROR: &'static str = "Instance query";
+    const QUERY: &'static str = r#"
+PREFIX nifi: <https://w3id.org/conn/nifi#>
+SELECT ?instance ?url ?group ?converter ?credentials WHERE {
+    ?instance a nifi:Instance;
+      nifi:url ?url.
+    OPTIONAL { ?instance nifi:group ?group }
+    OPTIONAL { ?instance nifi:converter ?converter }
+    OPTIONAL { ?instance nifi:credentials ?credentials }
+}
+"#;
+    type Output = InstanceOutput;
+}
+
+#[derive(Debug, Query)]
+struct RunsOnOutput {
+    pub subject: QueryField<Term, "subject">,
+    pub instance: QueryField<Term, "instance">,
+}
+
+struct RunsOnQuery;
+impl Queryable for RunsOnQuery {
+    const ERROR: &'static str = "Runs on query";
+    const QUERY: &'static str = r#"
+PREFIX nifi: <https://w3id.org/conn/nifi#>
+SELECT ?subject ?instance WHERE {
+    ?subject nifi:runsOn ?instance.
+}
+"#;
+    type Output = RunsOnOutput;
+}
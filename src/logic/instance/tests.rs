@@ -0,0 +1,89 @@
+use oxigraph::io::GraphFormat;
+use oxigraph::model::{GraphName, GraphNameRef, NamedNode, NamedNodeRef, Term};
+use oxigraph::store::Store;
+
+use super::Instances;
+use crate::client::Nifi;
+use crate::sparql::Dataset;
+
+const INPUT_GRAPH: &str = "http://example.com/input";
+const DEFAULT_URL: &str = "http://localhost:8091/nifi-api";
+
+const INPUT: &str = r#"
+@prefix nifi: <https://w3id.org/conn/nifi#> .
+@prefix ex: <http://example.com/> .
+
+ex:transform a nifi:Instance;
+  nifi:url "http://transform:8080/nifi-api";
+  nifi:group "pipelines".
+
+ex:assigned nifi:runsOn ex:transform.
+ex:unknown nifi:runsOn ex:missing.
+"#;
+
+fn load(input: &str) -> Result<Instances, String> {
+    let store = Store::new().unwrap();
+    store
+        .load_graph(
+            input.as_bytes(),
+            GraphFormat::Turtle,
+            GraphNameRef::NamedNode(NamedNodeRef::new(INPUT_GRAPH).unwrap()),
+            None,
+        )
+        .unwrap();
+
+    let dataset = Dataset {
+        store: &store,
+        graphs: vec![GraphName::NamedNode(NamedNode::new(INPUT_GRAPH).unwrap())],
+    };
+    let default = Nifi::new(DEFAULT_URL, "root", None, None).unwrap();
+    Instances::load(&dataset, &default)
+}
+
+fn node(local: &str) -> Term {
+    NamedNode::new(format!("http://example.com/{}", local))
+        .unwrap()
+        .into()
+}
+
+#[test]
+fn processors_run_on_their_instance() {
+    let instances = load(INPUT).unwrap();
+
+    let index = instances.runs_on(&node("assigned"));
+    assert_eq!(index, 1);
+    assert_eq!(
+        instances.client(index).url(),
+        "http://transform:8080/nifi-api"
+    );
+    assert_eq!(instances.client(index).group, "pipelines");
+}
+
+#[test]
+fn processors_default_to_the_given_instance() {
+    let instances = load(INPUT).unwrap();
+
+    assert_eq!(instances.runs_on(&node("unknown")), 0);
+    assert_eq!(instances.runs_on(&node("unassigned")), 0);
+    assert_eq!(instances.client(0).url(), DEFAULT_URL);
+    assert_eq!(instances.client(0).group, "root");
+}
+
+#[test]
+fn credentials_come_from_the_environment() {
+    let input = |variable: &str| {
+        format!(
+            "{}\n<http://example.com/transform> <https://w3id.org/conn/nifi#credentials> \"{}\".",
+            INPUT, variable
+        )
+    };
+
+    let missing = load(&input("NIFI_RUNNER_TEST_MISSING_TOKEN"));
+    assert!(missing
+        .err()
+        .unwrap()
+        .contains("NIFI_RUNNER_TEST_MISSING_TOKEN"));
+
+    std::env::set_var("NIFI_RUNNER_TEST_TOKEN", "token");
+    assert!(load(&input("NIFI_RUNNER_TEST_TOKEN")).is_ok());
+}
@@ -1,60 +1,430 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::path::{Path, PathBuf};
 
-use crate::logic::reader::{self, add_channel_reader};
-use crate::logic::writer::{self, add_channel_writer};
-use crate::logic::{import_file_to_store, import_reader_to_store};
+use crate::cache::OntologyCache;
+use crate::channels::append_ontologies;
+use crate::datatype::Datatype;
+use crate::logic::nodes::add_channels;
+use crate::logic::site_to_site;
+use crate::logic::{
+    connect, content_type, import_inputs, import_ontology, import_ontology_file, import_stdin,
+    ChannelPlugins, ChannelRegistry, ChannelSide, InputOptions, Instances, ParseErrors,
+    ParseOptions,
+};
 use crate::sparql::{
-    execute_query, get_parameter_solutions, NifiLinkQuery, NifiLinkQueryOutput, ProcessorQuery,
-    QuerySolutionOutput, QueryString, ShaclType,
+    execute_query, get_parameter_solutions, materialize_rdfs, ConvertingProcessorQuery, Dataset,
+    NifiChannelLinkOutput, NifiLinkQuery, ProcessorQuery, QuerySolutionOutput, ShaclType,
+    TypeVersionOutput, TypeVersionQuery, UsedTypesQuery,
+};
+use oxigraph::model::vocab::rdf;
+use oxigraph::model::{
+    GraphName, Literal, NamedNode, NamedNodeRef, NamedOrBlankNode, QuadRef, SubjectRef, Term,
+    TermRef,
 };
-use oxigraph::model::{GraphNameRef, Literal, NamedNodeRef, QuadRef, SubjectRef, Term, TermRef};
 use oxigraph::store::Store;
 
 use crate::client::Nifi;
-use crate::models::{Component, ConnectionEntity, ProcessorDTO};
+use crate::models::{BundleDTO, Component, FunnelDTO, ProcessorDTO};
+use crate::rdf::{path_key, prefix::NIFI};
 
 pub const ID_TERM: &str = "http://example.com/ns#testing+id";
+/// Graph with the ontologies of the nifi types and channels
+pub const ONTOLOGY_GRAPH: &str = "http://example.com/ns#ontology";
+/// Graph with the ids of the created nifi components, cleared for every pipeline
+pub const RUNTIME_GRAPH: &str = "http://example.com/ns#runtime";
+/// Graph with the statements entailed by the ontology, cleared for every pipeline
+pub const INFERRED_GRAPH: &str = "http://example.com/ns#inferred";
+
+const INTERNAL_GRAPHS: [&str; 3] = [ONTOLOGY_GRAPH, RUNTIME_GRAPH, INFERRED_GRAPH];
 
-pub async fn startup(client: Nifi, ontology: String, input: Option<String>, start: bool) {
+/// Every input document is loaded in its own graph. Named graphs in the inputs are pipelines,
+/// each deployed in a new process group, the documents themselves are context shared by all of
+/// them. Without named graphs the documents are deployed in the current process group.
+/// Processors with `nifi:runsOn` are deployed on that nifi instance instead of the given one
+pub async fn startup(
+    client: Nifi,
+    ontology: Option<String>,
+    cache_dir: PathBuf,
+    channel_dirs: Vec<PathBuf>,
+    plugins: &ChannelPlugins,
+    input: InputOptions,
+    pipeline: Option<String>,
+    start: bool,
+) -> Result<(), Box<dyn Error>> {
     let store = Store::new().unwrap();
+    let options = &input.parse;
+    let mut errors = Vec::new();
 
-    if let Some(input) = input {
-        println!("Loading files {}", input);
-        import_file_to_store(input, &store).expect("Load file to store");
-    } else {
+    let loaded = if input.locations.is_empty() {
         println!("Loading files stdin");
-        import_reader_to_store(std::io::stdin().lock(), &store).expect("Import in store");
+        import_stdin(options, &store)
+    } else {
+        import_inputs(&input.locations, options, &store)
+    };
+    let documents = match loaded {
+        Err(e) if !options.keep_going => return Err(e.into()),
+        Err(ParseErrors(e)) => {
+            errors.extend(e);
+            Vec::new()
+        }
+        Ok(documents) => documents,
+    };
+
+    let cached = ontology.is_none();
+    if let Some(ontology) = ontology {
+        println!("Loaded ontology {}", ontology);
+        if let Err(ParseErrors(e)) = import_ontology(
+            ontology,
+            &ParseOptions {
+                keep_going: options.keep_going,
+                ..ParseOptions::default()
+            },
+            &store,
+        ) {
+            errors.extend(e);
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(ParseErrors(errors).into());
+    }
+
+    let documents: Vec<GraphName> = documents
+        .iter()
+        .map(|x| NamedNode::new_unchecked(x).into())
+        .collect();
+
+    let mut pipelines = pipeline_graphs(&store, &documents);
+    if let Some(pipeline) = pipeline {
+        pipelines.retain(|x| x.as_str() == pipeline);
+        if pipelines.is_empty() {
+            return Err(format!("Unknown pipeline {}", pipeline).into());
+        }
+    }
+
+    if cached {
+        let mut graphs = documents.clone();
+        graphs.extend(pipelines.iter().cloned().map(GraphName::from));
+        let dataset = Dataset {
+            store: &store,
+            graphs,
+        };
+        load_cached_ontologies(&client, &cache_dir, &dataset).await;
+    }
+
+    append_ontologies(&channel_dirs, plugins, &store)?;
+
+    let pipelines: Vec<Option<NamedNode>> = if pipelines.is_empty() {
+        vec![None]
+    } else {
+        pipelines.into_iter().map(Some).collect()
+    };
+
+    // Every pipeline is checked before anything is created, an invalid pipeline would leave the
+    // pipelines before it deployed
+    let mut plans = Vec::new();
+    for pipeline in pipelines {
+        let dataset = pipeline_dataset(&store, &documents, pipeline.as_ref());
+        let instances = Instances::load(&dataset, &client)?;
+        let processors = planned_processors(&dataset, pipeline.as_ref())?;
+        check_converters(&dataset, &instances, &processors)?;
+        plans.push(Plan {
+            pipeline,
+            instances,
+            processors,
+        });
+    }
+
+    for plan in plans {
+        if let Some(ref pipeline) = plan.pipeline {
+            println!("Deploying pipeline {}", pipeline.as_str());
+        }
+        deploy(&store, plugins, &documents, plan, start).await?;
+    }
+
+    Ok(())
+}
+
+/// Named graphs of the inputs that are not a document, sorted
+fn pipeline_graphs(store: &Store, documents: &[GraphName]) -> Vec<NamedNode> {
+    let mut out: Vec<NamedNode> = store
+        .named_graphs()
+        .flatten()
+        .filter_map(|x| match x {
+            NamedOrBlankNode::NamedNode(x) => Some(x),
+            NamedOrBlankNode::BlankNode(_) => {
+                eprintln!("Skipping a pipeline without IRI");
+                None
+            }
+        })
+        .filter(|x| !INTERNAL_GRAPHS.contains(&x.as_str()))
+        .filter(|x| !documents.contains(&GraphName::from(x.clone())))
+        .collect();
+    out.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    out
+}
+
+/// Name of the process group of a pipeline, the last segment of its IRI
+fn pipeline_name(pipeline: &NamedNode) -> &str {
+    pipeline
+        .as_str()
+        .trim_end_matches(['/', '#'])
+        .rsplit(['/', '#'])
+        .next()
+        .filter(|x| !x.is_empty())
+        .unwrap_or(pipeline.as_str())
+}
+
+/// The processors of a pipeline on one nifi instance
+pub(super) struct Part {
+    pub(super) instance: usize,
+    pub(super) client: Nifi,
+    pub(super) procs: HashMap<String, Component<ProcessorDTO>>,
+}
+
+/// A processor to create, with its nifi properties and bundle
+pub(super) type Planned = (
+    Vec<QuerySolutionOutput>,
+    Vec<(String, String)>,
+    Option<BundleDTO>,
+);
+
+/// The documents or pipeline to deploy, checked before anything is created
+struct Plan {
+    pipeline: Option<NamedNode>,
+    instances: Instances,
+    processors: Vec<Planned>,
+}
+
+/// The documents, the pipeline and the internal graphs, with the statements they entail
+pub(super) fn pipeline_dataset<'a>(
+    store: &'a Store,
+    documents: &[GraphName],
+    pipeline: Option<&NamedNode>,
+) -> Dataset<'a> {
+    for graph in [RUNTIME_GRAPH, INFERRED_GRAPH] {
+        store
+            .clear_graph(NamedNodeRef::new(graph).unwrap())
+            .unwrap();
+    }
+
+    let mut graphs = documents.to_vec();
+    graphs.extend(pipeline.cloned().map(GraphName::from));
+    graphs.extend(INTERNAL_GRAPHS.map(|x| GraphName::from(NamedNode::new_unchecked(x))));
+    let dataset = Dataset { store, graphs };
+
+    materialize_rdfs(&dataset, INFERRED_GRAPH);
+    assign_versions(&dataset);
+    dataset
+}
+
+/// The processors of the documents or the pipeline, an error when a property value doesn't match
+/// its datatype or a pinned bundle version doesn't exist
+pub(super) fn planned_processors(
+    dataset: &Dataset,
+    pipeline: Option<&NamedNode>,
+) -> Result<Vec<Planned>, String> {
+    // The documents are shared by every pipeline, a pipeline only creates its own processors
+    let owned = pipeline.map(|x| (x, typed_subjects(dataset.store, x)));
+
+    let mut processors = Vec::new();
+    for (subject, v) in get_parameter_solutions::<ProcessorQuery>(dataset) {
+        if let Some((pipeline, ref subjects)) = owned {
+            if !subjects.contains(&subject) {
+                eprintln!(
+                    "Skipping processor {}, it is not part of pipeline {}",
+                    subject,
+                    pipeline.as_str()
+                );
+                continue;
+            }
+        }
+
+        let properties = processor_properties(&v)?;
+        let bundle = select_bundle(&v)?;
+        processors.push((v, properties, bundle));
+    }
+
+    Ok(processors)
+}
+
+/// Processors that convert their flow files to a serialization need a converter on their
+/// instance, without one the flow files would only get a `mime.type`
+pub(super) fn check_converters(
+    dataset: &Dataset,
+    instances: &Instances,
+    processors: &[Planned],
+) -> Result<(), String> {
+    let planned: HashSet<&Term> = processors.iter().map(|(v, _, _)| &v[0].subject.0).collect();
+
+    for sol in execute_query::<ConvertingProcessorQuery>(dataset) {
+        if !planned.contains(&sol.subject.0) {
+            continue;
+        }
+        let to = match content_type(&sol.serialization) {
+            Some(x) => x,
+            None => continue,
+        };
+
+        let client = instances.client(instances.runs_on(&sol.subject.0));
+        if client.converter().is_none() {
+            return Err(format!(
+                "The flow files of {} are converted to {}, but no converter is set for {}: pass \
+                 --converter or set nifi:converter on its instance",
+                sol.subject.0,
+                to.mime(),
+                client.url()
+            ));
+        }
     }
 
-    println!("Loaded ontology {}", ontology);
-    import_file_to_store(ontology, &store).expect("Load file to store");
+    Ok(())
+}
 
-    writer::append_ontology(&store).expect("Loading WS writer ontology");
-    reader::append_ontology(&store).expect("Loading WS reader ontology");
+/// Creates the processors, links and channels of the documents and the pipeline. A pipeline gets
+/// a new process group on every instance it runs on, otherwise the process group of the instance
+/// is used
+async fn deploy(
+    store: &Store,
+    plugins: &ChannelPlugins,
+    documents: &[GraphName],
+    plan: Plan,
+    start: bool,
+) -> Result<(), Box<dyn Error>> {
+    let Plan {
+        pipeline,
+        instances,
+        processors,
+    } = plan;
 
-    let mut create_processors = HashMap::new();
+    let dataset = pipeline_dataset(store, documents, pipeline.as_ref());
+    let registry = ChannelRegistry::load(&dataset);
 
-    let per_subject = get_parameter_solutions::<ProcessorQuery>(&store);
+    let mut parts: Vec<Part> = Vec::new();
+
+    for (v, properties, bundle) in processors {
+        let instance = instances.runs_on(&v[0].subject);
+        let index = match parts.iter().position(|x| x.instance == instance) {
+            Some(x) => x,
+            None => {
+                let client = instances.client(instance);
+                let client = match pipeline {
+                    Some(ref pipeline) => {
+                        let group = client
+                            .new_process_group(pipeline_name(pipeline))
+                            .await
+                            .expect("Creating process group");
+                        client.change_group(&group.id)
+                    }
+                    None => client.change_group(&client.group),
+                };
+                parts.push(Part {
+                    instance,
+                    client,
+                    procs: HashMap::new(),
+                });
+                parts.len() - 1
+            }
+        };
+
+        let part = &mut parts[index];
+        let (id, proc) = create_processor(&part.client, v, properties, bundle, store).await?;
+        part.procs.insert(id, proc);
+    }
 
-    for v in per_subject.into_values() {
-        let (id, proc) = create_processor(&client, v, &store).await;
-        create_processors.insert(id, proc);
+    let mut channels: Vec<(Term, Vec<NifiChannelLinkOutput>)> = Vec::new();
+    for link in execute_query::<NifiLinkQuery>(&dataset) {
+        match channels.iter_mut().find(|x| x.0 == link.channel.0) {
+            Some((_, links)) => links.push(link),
+            None => channels.push((link.channel.0.clone(), vec![link])),
+        }
     }
 
-    for link in execute_query::<NifiLinkQuery>(&store) {
-        add_nifi_link(&client, link, &create_processors).await;
+    for (_, links) in channels {
+        if let Some(used) = funnel_parts(&parts, &links) {
+            if add_funnel(&used, links, start).await.is_none() {
+                eprintln!("Failed to add funnel");
+            }
+            continue;
+        }
+
+        for link in links {
+            let source = parts
+                .iter()
+                .find(|x| x.procs.contains_key(&link.source_id as &str));
+            let target = parts
+                .iter()
+                .find(|x| x.procs.contains_key(&link.target_id as &str));
+
+            match (source, target) {
+                (Some(source), Some(target)) if source.instance != target.instance => {
+                    add_remote_link(source, target, link, start).await;
+                }
+                (Some(source), _) => {
+                    add_nifi_link(&source.client, link, &source.procs, start).await;
+                }
+                _ => {}
+            }
+        }
     }
 
-    add_channel_writer(&client, &store, &create_processors, start).await;
-    add_channel_reader(&client, &store, &create_processors, start).await;
+    for part in &parts {
+        for side in [ChannelSide::Writer, ChannelSide::Reader] {
+            add_channels(
+                &part.client,
+                &dataset,
+                &registry,
+                plugins,
+                &part.procs,
+                side,
+                start,
+            )
+            .await;
+        }
+    }
 
     if start {
-        for proc in create_processors.into_values() {
-            if let Err(e) = client.start_processor(&proc.id).await {
-                println!("Start processor failed\n{:?}", e);
+        for part in parts {
+            for proc in part.procs.into_values() {
+                if let Err(e) = part.client.start_processor(&proc.id).await {
+                    println!("Start processor failed\n{:?}", e);
+                }
             }
         }
     }
+
+    Ok(())
+}
+
+/// Subjects with a type in the graph
+pub(super) fn typed_subjects(store: &Store, graph: &NamedNode) -> HashSet<Term> {
+    store
+        .quads_for_pattern(None, Some(rdf::TYPE), None, Some(graph.as_ref().into()))
+        .flatten()
+        .map(|x| x.subject.into())
+        .collect()
+}
+
+/// Loads the cached ontology of every nifi type used in the dataset
+async fn load_cached_ontologies(client: &Nifi, cache_dir: &Path, dataset: &Dataset<'_>) {
+    let version = client.nifi_version().await.expect("Nifi version");
+    let cache = OntologyCache::open(cache_dir, &version).expect("Open ontology cache");
+
+    for used in execute_query::<UsedTypesQuery>(dataset) {
+        let files = cache.lookup(used.ty.as_str());
+        if files.is_empty() {
+            eprintln!(
+                "No cached ontology for {}, generate it with `list types --full`",
+                used.ty.as_str()
+            );
+        }
+
+        for file in files {
+            println!("Loaded cached ontology {}", file.display());
+            import_ontology_file(file, dataset.store).expect("Load file to store");
+        }
+    }
 }
 
 pub fn as_subject_ref(t: TermRef) -> SubjectRef {
@@ -65,16 +435,81 @@ pub fn as_subject_ref(t: TermRef) -> SubjectRef {
     }
 }
 
+/// The nifi properties of a processor node, values are normalized to their datatype
+pub(super) fn processor_properties(
+    solution: &[QuerySolutionOutput],
+) -> Result<Vec<(String, String)>, String> {
+    let mut properties = Vec::new();
+
+    for sol in solution {
+        // Channels are linked later on, controller services are referenced by their id
+        let object = match (&sol.shacl_type, &sol.value.0) {
+            (_, Term::Literal(v)) => v.value().to_string(),
+            (ShaclType::Class(_), _) => continue,
+            (_, value) => {
+                return Err(format!(
+                    "Expected a literal for {} of {}, found {}",
+                    sol.path.0, sol.subject.0, value
+                ))
+            }
+        };
+
+        // Ontologies without a mapping use the local name of the property
+        let key = match sol.nifi_key {
+            Some(ref key) => key.0.clone(),
+            None => match sol.path.as_str().strip_prefix(NIFI.1).and_then(path_key) {
+                Some(key) => key,
+                None => {
+                    return Err(format!(
+                        "No nifi key for {} of {}",
+                        sol.path.0, sol.subject.0
+                    ))
+                }
+            },
+        };
+
+        // Expressions are only known at runtime
+        let datatype = match sol.shacl_type {
+            ShaclType::DataType(Term::NamedNode(ref dt)) if !object.contains("${") => {
+                Datatype::from_iri(dt.as_str())
+            }
+            _ => None,
+        };
+
+        let object = match datatype.map(|x| x.normalize(&object)) {
+            Some(Ok(x)) => x,
+            Some(Err(e)) => {
+                return Err(format!(
+                    "Invalid value {:?} for {} of {}: {}",
+                    object, key, sol.subject.0, e
+                ))
+            }
+            None => object,
+        };
+
+        properties.push((key, object));
+    }
+
+    Ok(properties)
+}
+
 async fn create_processor(
     client: &Nifi,
     solution: Vec<QuerySolutionOutput>,
+    properties: Vec<(String, String)>,
+    bundle: Option<BundleDTO>,
     store: &Store,
-) -> (String, Component<ProcessorDTO>) {
+) -> Result<(String, Component<ProcessorDTO>), String> {
     println!("Creating processor {:?}", solution[0].ty);
     let mut proc = client
-        .new_processor(&solution[0].ty)
+        .new_processor(&solution[0].ty, bundle.as_ref())
         .await
-        .expect("Nifi failed me again");
+        .map_err(|e| {
+            format!(
+                "Failed to create processor {} of {}\n{:?}",
+                solution[0].ty.0, solution[0].subject.0, e
+            )
+        })?;
 
     let v = Literal::new_simple_literal(&proc.id);
 
@@ -83,53 +518,320 @@ async fn create_processor(
             subject: as_subject_ref(solution[0].subject.0.as_ref()),
             predicate: NamedNodeRef::new(ID_TERM).unwrap().into(),
             object: v.as_ref().into(),
-            graph_name: GraphNameRef::DefaultGraph,
+            graph_name: NamedNodeRef::new(RUNTIME_GRAPH).unwrap().into(),
         })
         .unwrap();
 
-    for sol in solution {
-        if let ShaclType::Class(_) = sol.shacl_type {
-            continue;
-        }
-
-        let key = sol.nifi_key.unwrap();
-        let object = match sol.value.0 {
-            Term::Literal(v) => v.destruct().0,
-            _ => panic!("Not a literal"),
-        };
-
+    for (key, value) in properties {
         proc.component
             .comp
             .config
             .properties
-            .insert(key.0, Some(object));
+            .insert(key, Some(value));
     }
 
     println!("Updating processor");
     client
         .update_processor(&proc.id, &proc)
         .await
-        .expect("Nifi failed med");
+        .map_err(|e| {
+            format!(
+                "Failed to configure processor {} of {}\n{:?}",
+                solution[0].ty.0, solution[0].subject.0, e
+            )
+        })?;
+
+    Ok((proc.id, proc.component))
+}
+
+/// Picks the bundle pinned with `nifi:bundleVersion`, or the most recent one. A pin that matches
+/// no bundle is an error, nifi would pick an arbitrary one
+pub(super) fn select_bundle(solution: &[QuerySolutionOutput]) -> Result<Option<BundleDTO>, String> {
+    let bundle: BundleDTO = match solution.iter().find_map(|x| x.bundle.clone()) {
+        Some(x) => x.into(),
+        None => return Ok(None),
+    };
+
+    if let Some(pinned) = solution.iter().find_map(|x| x.pinned_version.as_ref()) {
+        if pinned.0 != bundle.version {
+            return Err(format!(
+                "No bundle with version {} found for {} of {}",
+                pinned.0, solution[0].ty.0, solution[0].subject.0
+            ));
+        }
+    }
+
+    Ok(Some(bundle))
+}
+
+/// Types every processor with the class of one bundle of its type, the pinned
+/// `nifi:bundleVersion` or else the most recent one
+pub(super) fn assign_versions(dataset: &Dataset) {
+    let mut per_subject: HashMap<Term, Vec<TypeVersionOutput>> = HashMap::new();
+    for sol in execute_query::<TypeVersionQuery>(dataset) {
+        per_subject
+            .entry(sol.subject.0.clone())
+            .or_default()
+            .push(sol);
+    }
+
+    for (subject, versions) in per_subject {
+        let pinned = versions.iter().find_map(|x| x.pinned_version.as_ref());
+        let pinned = pinned.and_then(|p| versions.iter().find(|x| x.bundle_version.0 == p.0));
+
+        if pinned.is_none() && versions.len() > 1 {
+            println!(
+                "Multiple bundles found for {}, pin one with nifi:bundleVersion",
+                subject
+            );
+        }
+
+        let version = pinned.or_else(|| {
+            versions
+                .iter()
+                .max_by(|a, b| compare_versions(&a.bundle_version, &b.bundle_version))
+        });
+
+        if let Some(version) = version {
+            dataset
+                .store
+                .insert(QuadRef {
+                    subject: as_subject_ref(subject.as_ref()),
+                    predicate: rdf::TYPE,
+                    object: version.version.as_ref().into(),
+                    graph_name: NamedNodeRef::new(INFERRED_GRAPH).unwrap().into(),
+                })
+                .unwrap();
+        }
+    }
+}
+
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parts = |x: &str| -> Vec<u64> {
+        x.split(|c: char| !c.is_ascii_digit())
+            .filter_map(|x| x.parse().ok())
+            .collect()
+    };
+    parts(a).cmp(&parts(b))
+}
+
+/// Every writer of a channel is linked to every reader. When that takes more connections than
+/// linking all writers and readers to funnels, the parts that run a writer or reader are returned
+pub(super) fn funnel_parts<'a>(
+    parts: &'a [Part],
+    links: &[NifiChannelLinkOutput],
+) -> Option<Vec<&'a Part>> {
+    let sources: HashSet<(&str, &str)> = links
+        .iter()
+        .map(|x| (x.source_id.as_str(), x.key.as_str()))
+        .collect();
+    let targets: HashSet<&str> = links.iter().map(|x| x.target_id.as_str()).collect();
+
+    // Direct links take s * t connections, a funnel takes one per writer and one per reader.
+    // With a single writer or reader, or two of each, that is not fewer and the funnel only adds
+    // a component. Across instances the funnels also save site-to-site links, but small
+    // channels stay plain links there too
+    if sources.len() * targets.len() <= sources.len() + targets.len() {
+        return None;
+    }
+
+    let mut used: Vec<&Part> = Vec::new();
+    for link in links {
+        for id in [link.source_id.as_str(), link.target_id.as_str()] {
+            let part = parts.iter().find(|x| x.procs.contains_key(id))?;
+            if !used.iter().any(|x| x.instance == part.instance) {
+                used.push(part);
+            }
+        }
+    }
+
+    Some(used)
+}
+
+/// Links the writers of a channel to a funnel and the funnel to its readers. Across instances
+/// every instance gets a funnel for its writers and one for its readers, each writer funnel
+/// has one site-to-site link to the reader funnel of every other instance. Flow files from
+/// other instances only reach the reader funnel, so they are not sent back
+async fn add_funnel(parts: &[&Part], links: Vec<NifiChannelLinkOutput>, start: bool) -> Option<()> {
+    println!(
+        "Adding funnels for {} links on {} instances",
+        links.len(),
+        parts.len()
+    );
+    let serialization = links[0].serialization.as_ref().map(|x| &x.0);
+    let shared = parts.len() == 1;
+
+    let mut funnels = Vec::new();
+    for part in parts {
+        let writes = links
+            .iter()
+            .any(|x| part.procs.contains_key(x.source_id.as_str()));
+        let reads = links
+            .iter()
+            .any(|x| part.procs.contains_key(x.target_id.as_str()));
+
+        let writer = if writes {
+            Some(new_funnel(&part.client).await?)
+        } else {
+            None
+        };
+        let reader = match (reads, &writer) {
+            (true, Some(writer)) if shared => Some(writer.clone()),
+            (true, _) => Some(new_funnel(&part.client).await?),
+            (false, _) => None,
+        };
+        funnels.push((writer, reader));
+    }
+
+    let mut sources = HashSet::new();
+    let mut targets = HashSet::new();
+    for link in &links {
+        if sources.insert((link.source_id.as_str(), link.key.as_str())) {
+            let index = parts
+                .iter()
+                .position(|x| x.procs.contains_key(link.source_id.as_str()))?;
+            let part = parts[index];
+            let source = &part.procs[link.source_id.as_str()];
+            let funnel = funnels[index].0.as_ref()?;
+            let rel = Some(link.key.as_str());
+            if connect(&part.client, source, funnel, rel, None, start)
+                .await
+                .is_none()
+            {
+                eprintln!(
+                    "Failed to link {} ({}) to the funnel",
+                    link.source_id.as_str(),
+                    link.key.as_str()
+                );
+                return None;
+            }
+        }
+
+        if targets.insert(link.target_id.as_str()) {
+            let index = parts
+                .iter()
+                .position(|x| x.procs.contains_key(link.target_id.as_str()))?;
+            let part = parts[index];
+            let target = &part.procs[link.target_id.as_str()];
+            let funnel = funnels[index].1.as_ref()?;
+            // A shared funnel converts on the way to the readers, otherwise the flow files are
+            // converted before they reach the reader funnel
+            let serialization = if shared {
+                serialization.map(|x| (x, ChannelSide::Writer))
+            } else {
+                None
+            };
+            if connect(&part.client, funnel, target, None, serialization, start)
+                .await
+                .is_none()
+            {
+                eprintln!("Failed to link the funnel to {}", link.target_id.as_str());
+                return None;
+            }
+        }
+    }
+
+    if shared {
+        return Some(());
+    }
 
-    (proc.id, proc.component)
+    for (i, source) in parts.iter().enumerate() {
+        for (j, target) in parts.iter().enumerate() {
+            let (writer, reader) = match (&funnels[i].0, &funnels[j].1) {
+                (Some(writer), Some(reader)) => (writer, reader),
+                _ => continue,
+            };
+
+            let linked = if i == j {
+                let serialization = serialization.map(|x| (x, ChannelSide::Writer));
+                connect(&source.client, writer, reader, None, serialization, start).await
+            } else {
+                site_to_site::link(
+                    &source.client,
+                    writer,
+                    None,
+                    &target.client,
+                    reader,
+                    serialization,
+                    start,
+                )
+                .await
+            };
+
+            if linked.is_none() {
+                eprintln!(
+                    "Failed to link the funnels of {} and {}",
+                    source.client.url(),
+                    target.client.url()
+                );
+                return None;
+            }
+        }
+    }
+
+    Some(())
+}
+
+async fn new_funnel(client: &Nifi) -> Option<Component<FunnelDTO>> {
+    match client.new_funnel().await {
+        Ok(x) => Some(x.component),
+        Err(e) => {
+            eprintln!("Failed to create funnel\n{:?}", e);
+            None
+        }
+    }
+}
+
+/// Links processors on different instances with a site-to-site channel
+async fn add_remote_link(
+    source: &Part,
+    target: &Part,
+    link: NifiChannelLinkOutput,
+    start: bool,
+) -> Option<()> {
+    let source_proc = source.procs.get(&link.source_id as &str)?;
+    let target_proc = target.procs.get(&link.target_id as &str)?;
+
+    let serialization = link.serialization.as_ref().map(|x| &x.0);
+    let linked = site_to_site::link(
+        &source.client,
+        source_proc,
+        Some(&link.key),
+        &target.client,
+        target_proc,
+        serialization,
+        start,
+    )
+    .await;
+
+    if linked.is_none() {
+        eprintln!("Failed to link processors across instances");
+    }
+    linked
 }
 
 async fn add_nifi_link<'a>(
     client: &'a Nifi,
-    link: NifiLinkQueryOutput<QueryString<"key">>,
+    link: NifiChannelLinkOutput,
     procs: &'a HashMap<String, Component<ProcessorDTO>>,
+    start: bool,
 ) -> Option<()> {
     println!("Adding link between processors");
 
     let source = procs.get(&link.source_id as &str)?;
     let target = procs.get(&link.target_id as &str)?;
 
-    let body = ConnectionEntity::new(&source, &target, Some(&link.key));
-
-    client
-        .create_conection(body)
-        .await
-        .expect("Creating nifi connection");
-
-    Some(())
+    let serialization = link
+        .serialization
+        .as_ref()
+        .map(|x| (&x.0, ChannelSide::Writer));
+    connect(
+        client,
+        source,
+        target,
+        Some(&link.key),
+        serialization,
+        start,
+    )
+    .await
 }
@@ -1,56 +1,228 @@
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
-use std::sync::Arc;
-
+use crate::channels::read_template;
 use crate::client::Nifi;
-use crate::models::{Component, ProcessorDTO};
-use crate::util::*;
-use oxigraph::store::Store;
-use rio_api::parser::TriplesParser;
-use rio_turtle::{TurtleError, TurtleParser};
+use crate::models::{Component, ConnectionEntity, ConnectionTargetType, ProcessorDTO};
+use crate::rdf::Serialization;
+use oxigraph::model::NamedNode;
 
+mod channel;
+mod input;
+mod instance;
 mod logic;
-mod reader;
-mod writer;
+mod nodes;
+mod port;
+mod registry;
+mod site_to_site;
+#[cfg(test)]
+mod tests;
+pub use channel::*;
+pub use input::*;
+pub use instance::*;
 pub use logic::*;
+pub use registry::*;
 
-async fn template_file_id(client: &Nifi, location: &str) -> Option<String> {
-    let content = std::fs::read_to_string(location).ok()?;
-    client.upload_template(content).await.ok()
+const UPDATE_ATTRIBUTE: &str = "org.apache.nifi.processors.attributes.UpdateAttribute";
+const EXECUTE_STREAM_COMMAND: &str = "org.apache.nifi.processors.standard.ExecuteStreamCommand";
+
+/// Serialization of a `:serialization`, `:none` keeps the flow files as they are
+fn content_type(serialization: &NamedNode) -> Option<Serialization> {
+    match serialization.as_str() {
+        "https://w3id.org/conn#turtle" => Some(Serialization::Turtle),
+        "https://w3id.org/conn#nquads" => Some(Serialization::NQuads),
+        "https://w3id.org/conn#jsonld" => Some(Serialization::JsonLd),
+        "https://w3id.org/conn#none" => None,
+        other => {
+            eprintln!(
+                "Unknown serialization {}, the content type is not set",
+                other
+            );
+            None
+        }
+    }
 }
 
-pub fn import_reader_to_store<R: BufRead + Sized>(file: R, bl: &Store) -> std::io::Result<()> {
-    let parser = TurtleParser::new(file, None);
-    let mut mapper = RDFMapper::default();
-    parser
-        .into_iter::<_, TurtleError, _>(|triple| Ok(mapper.map_triple_to_quad(triple)))
-        .flatten()
-        .for_each(|q| {
-            bl.insert(&q).unwrap();
-        });
-
-    Ok(())
+/// Arguments of the `convert` command of the converter, separated by `;`. The writer side
+/// converts the flow files from their `mime.type`, the reader side parses them
+fn convert_arguments(to: Serialization, side: ChannelSide) -> String {
+    match side {
+        ChannelSide::Writer => format!("convert;--from=${{mime.type}};--to={}", to.mime()),
+        ChannelSide::Reader => format!("convert;--to={}", to.mime()),
+    }
 }
 
-pub fn import_file_to_store<P: AsRef<Path>>(location: P, bl: &Store) -> std::io::Result<()> {
-    import_reader_to_store(BufReader::new(File::open(location)?), &bl)
+/// Connects the source to the target. When the link has a serialization the flow files pass the
+/// `convert` command of the converter of the instance and an UpdateAttribute processor that sets
+/// their `mime.type`, flow files that don't parse are dropped
+async fn connect<I, O>(
+    client: &Nifi,
+    source: &Component<I>,
+    target: &Component<O>,
+    rel: Option<&str>,
+    serialization: Option<(&NamedNode, ChannelSide)>,
+    start: bool,
+) -> Option<()>
+where
+    for<'a> &'a I: Into<ConnectionTargetType>,
+    for<'a> &'a O: Into<ConnectionTargetType>,
+{
+    let (to, side) = match serialization.and_then(|(x, side)| Some((content_type(x)?, side))) {
+        Some(x) => x,
+        None => {
+            return create_connection(client, ConnectionEntity::new(source, target, rel)).await;
+        }
+    };
+
+    // Deploys check the converters up front, see `check_converters`
+    let converter = match client.converter() {
+        Some(x) => x,
+        None => {
+            eprintln!(
+                "No converter set for {}, the flow files can't be converted to {}",
+                client.url(),
+                to.mime()
+            );
+            return None;
+        }
+    };
+
+    let mut steps = Vec::new();
+    println!("Converting flow files to {}", to.mime());
+    let arguments = convert_arguments(to, side);
+    let properties = [
+        ("Command Path", converter),
+        ("Command Arguments", arguments.as_str()),
+    ];
+    let dropped = ["original", "nonzero status"];
+    let step = new_step(client, EXECUTE_STREAM_COMMAND, &properties, &dropped).await?;
+    steps.push((step, "output stream"));
+
+    println!("Setting content type {}", to.mime());
+    let step = new_step(client, UPDATE_ATTRIBUTE, &[("mime.type", to.mime())], &[]).await?;
+    steps.push((step, "success"));
+
+    create_connection(client, ConnectionEntity::new(source, &steps[0].0, rel)).await?;
+    for pair in steps.windows(2) {
+        let (from, from_rel) = &pair[0];
+        let link = ConnectionEntity::new(from, &pair[1].0, Some(*from_rel));
+        create_connection(client, link).await?;
+    }
+    let (last, last_rel) = steps.last().unwrap();
+    create_connection(client, ConnectionEntity::new(last, target, Some(*last_rel))).await?;
+
+    if start {
+        for (step, _) in &steps {
+            if let Err(e) = client.start_processor(&step.id).await {
+                eprintln!("Start processor failed\n{:?}", e);
+            }
+        }
+    }
+
+    Some(())
+}
+
+/// Creates a processor of a link with the given properties, the relationships that are not
+/// connected drop their flow files
+async fn new_step(
+    client: &Nifi,
+    ty: &str,
+    properties: &[(&str, &str)],
+    dropped: &[&str],
+) -> Option<Component<ProcessorDTO>> {
+    let mut proc = match client.new_processor(ty, None).await {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Failed to create {}\n{:?}", ty, e);
+            return None;
+        }
+    };
+
+    let config = &mut proc.component.comp.config;
+    for (key, value) in properties {
+        config
+            .properties
+            .insert(key.to_string(), Some(value.to_string()));
+    }
+    if !dropped.is_empty() {
+        config.auto_terminated = Some(dropped.iter().map(|x| x.to_string()).collect());
+    }
+
+    match client.update_processor(&proc.id, &proc).await {
+        Ok(x) => Some(x.component),
+        Err(e) => {
+            eprintln!("Failed to configure {}\n{:?}", ty, e);
+            None
+        }
+    }
 }
 
-#[async_trait::async_trait]
-pub trait Channel {
-    fn append_ontology(store: &Store) -> std::io::Result<()>;
-
-    // async fn add_channel_reader(
-    //     client: Nifi,
-    //     store: &Store,
-    //     procs: &HashMap<String, Component<ProcessorDTO>>,
-    // );
-    //
-    async fn add_channel_writer(
-        client: Arc<Nifi>,
-        store: Arc<Store>,
-        procs: Arc<HashMap<String, Component<ProcessorDTO>>>,
-    );
+async fn create_connection(client: &Nifi, connection: ConnectionEntity) -> Option<()> {
+    match client.create_conection(connection).await {
+        Ok(_) => Some(()),
+        Err(e) => {
+            eprintln!("Failed to create nifi connection\n{:?}", e);
+            None
+        }
+    }
+}
+
+/// Property key that sets the run schedule of a processor in a channel
+const RUN_SCHEDULE: &str = "Run Schedule";
+
+/// Sets the values of a channel on its process group. Keys like `ListFile/Input Directory` set
+/// the property of the processor with that name in the group, `ListFile/Run Schedule` sets its
+/// run schedule, other keys are variables of the group
+async fn configure_channel(
+    client: &Nifi,
+    values: impl Iterator<Item = (String, String)>,
+) -> Option<()> {
+    let (properties, variables): (Vec<_>, Vec<_>) = values.partition(|(key, _)| key.contains('/'));
+    client.set_variables(variables.into_iter()).await.ok()?;
+
+    if properties.is_empty() {
+        return Some(());
+    }
+
+    let flow = client.get_flow().await.ok()?;
+    for mut processor in flow.group_flow.flow.processors {
+        let config = &mut processor.component.comp.config;
+        let mut changed = false;
+
+        for (key, value) in &properties {
+            let (name, property) = key.split_once('/').unwrap();
+            if name != processor.component.comp.name {
+                continue;
+            }
+
+            changed = true;
+            if property == RUN_SCHEDULE {
+                config.scheduling_period = Some(value.clone());
+            } else {
+                config
+                    .properties
+                    .insert(property.to_string(), Some(value.clone()));
+            }
+        }
+
+        if changed {
+            if let Err(e) = client.update_processor(&processor.id, &processor).await {
+                eprintln!(
+                    "Failed to configure {}\n{:?}",
+                    processor.component.comp.name, e
+                );
+                return None;
+            }
+        }
+    }
+
+    Some(())
+}
+
+async fn template_file_id(client: &Nifi, template: &str) -> Option<String> {
+    let content = match read_template(template) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Failed to read template {}: {}", template, e);
+            return None;
+        }
+    };
+    client.upload_template(content).await.ok()
 }
@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use derive::Query;
+use oxigraph::model::{NamedNode, Term};
+
+use super::{channel_config, ChannelContext, ChannelPlugins, ChannelRegistry, ChannelSide};
+use crate::{
+    client::Nifi,
+    logic::connect,
+    models::{Component, PortDTO, ProcessorDTO},
+    sparql::{
+        execute_query, get_parameter_solutions, Dataset, QueryField, QueryString, Queryable, Sol,
+        WithSubject,
+    },
+};
+
+/// Creates the reader or writer nodes of the processors with their channel plugin and links them
+/// to their processors
+pub async fn add_channels(
+    client: &Nifi,
+    dataset: &Dataset<'_>,
+    registry: &ChannelRegistry,
+    plugins: &ChannelPlugins,
+    procs: &HashMap<String, Component<ProcessorDTO>>,
+    side: ChannelSide,
+    start: bool,
+) {
+    let mut ctx = ChannelContext::new(client, registry);
+    let mut ports: HashMap<String, Component<PortDTO>> = HashMap::new();
+
+    let sols = get_parameter_solutions::<ChannelNodeQuery>(dataset);
+    let mut channels = Vec::new();
+
+    for sol in sols.into_values() {
+        let sol: Vec<_> = sol
+            .into_iter()
+            .filter(|x| x.class.as_str() == side.class())
+            .collect();
+
+        // Nodes of processors on other instances are created with their client
+        if sol.is_empty() || !procs.contains_key(sol[0].processor_id.as_str()) {
+            continue;
+        }
+
+        println!("Creating {}", side.name());
+        let channel = match channel_config(&sol, side) {
+            Some(config) => {
+                plugins
+                    .create(&mut ctx, dataset.store, config, &mut ports)
+                    .await
+            }
+            None => None,
+        };
+
+        match channel {
+            Some(channel) => channels.push(channel),
+            None => eprintln!("Failed to add {}", side.name()),
+        }
+    }
+
+    for link in execute_query::<ChannelLinkQuery>(dataset) {
+        if link.class.as_str() != side.class() || !procs.contains_key(link.processor_id.as_str()) {
+            continue;
+        }
+
+        if add_link(client, link, side, &ports, procs, start)
+            .await
+            .is_none()
+        {
+            eprintln!("Failed to add link");
+        }
+    }
+
+    ctx.finish().await;
+
+    if start {
+        for channel in channels {
+            if let Err(e) = channel.start().await {
+                eprintln!("Failed to start channel\n{:?}", e);
+            }
+        }
+    }
+}
+
+/// Readers feed their processor, writers get the flow files of a relationship of their processor
+async fn add_link(
+    client: &Nifi,
+    link: ChannelLinkOutput,
+    side: ChannelSide,
+    ports: &HashMap<String, Component<PortDTO>>,
+    procs: &HashMap<String, Component<ProcessorDTO>>,
+    start: bool,
+) -> Option<()> {
+    println!("adding link to {}", side.name());
+    let processor = procs.get(link.processor_id.as_str())?;
+    let port = ports.get(link.port_id.as_str())?;
+    let serialization = link.serialization.as_ref().map(|x| (&x.0, side));
+
+    match side {
+        ChannelSide::Reader => connect(client, port, processor, None, serialization, start).await,
+        ChannelSide::Writer => {
+            let key = match link.key {
+                Some(ref key) => key.as_str(),
+                None => {
+                    eprintln!(
+                        "No relationship found for the writer of {}",
+                        link.processor_id.as_str()
+                    );
+                    return None;
+                }
+            };
+            connect(client, processor, port, Some(key), serialization, start).await
+        }
+    }
+}
+
+/// A value of a reader or writer node
+#[derive(Clone, Debug, Query)]
+pub struct ChannelNodeOutput {
+    /// `:ReaderChannel` or `:WriterChannel`
+    pub class: QueryField<NamedNode, "class">,
+    pub subject: QueryField<Term, "subject">,
+
+    pub nifi_key: QueryField<String, "nifi_key">,
+    pub value: QueryField<String, "value">,
+    pub channel_type: QueryField<Term, "channel_type">,
+    pub processor_id: QueryField<String, "processor_id">,
+}
+
+impl WithSubject for ChannelNodeOutput {
+    fn subject(&self) -> &Term {
+        &self.subject
+    }
+}
+
+struct ChannelNodeQuery;
+impl Queryable for ChannelNodeQuery {
+    type Output = ChannelNodeOutput;
+    const ERROR: &'static str = "Channel node query";
+
+    const QUERY: &'static str = r#"
+PREFIX nifi: <https://w3id.org/conn/nifi#>
+PREFIX fno: <https://w3id.org/function/ontology#>
+PREFIX fnom: <https://w3id.org/function/vocabulary/mapping#>
+PREFIX sh: <http://www.w3.org/ns/shacl#>
+PREFIX : <https://w3id.org/conn#> 
+                
+SELECT DISTINCT ?class ?channel_type ?subject ?nifi_key ?value ?processor_id WHERE {
+    VALUES ?class { :ReaderChannel :WriterChannel }
+
+    ?processorTy a nifi:NifiProcess.
+
+    [] sh:targetClass ?processorTy;
+       sh:property [
+         sh:class ?class;
+         sh:path ?path;
+       ].
+
+    ?channel_type nifi:mapping [
+      fno:parameterMapping [
+        fnom:functionParameter ?p;
+        fnom:implementationParameterPosition ?nifi_key;
+      ]
+    ].
+
+    _:processor a ?processorTy;
+       <http://example.com/ns#testing+id> ?processor_id;
+       ?path ?subject.
+
+    [] sh:targetClass ?channel_type;
+        sh:property [
+          sh:path ?p;
+        ].
+
+    ?subject a ?channel_type;
+      ?p ?value.
+}
+"#;
+}
+
+/// A processor and the port of one of its reader or writer nodes
+#[derive(Debug, Query)]
+struct ChannelLinkOutput {
+    pub class: QueryField<NamedNode, "class">,
+    pub processor_id: QueryString<"processor_id">,
+    pub port_id: QueryString<"port_id">,
+    /// Relationship of the processor that feeds a writer
+    pub key: Option<QueryString<"key">>,
+    pub serialization: Option<QueryField<NamedNode, "serialization">>,
+}
+
+struct ChannelLinkQuery;
+impl Queryable for ChannelLinkQuery {
+    const ERROR: &'static str = "Channel link query";
+
+    const QUERY: &'static str = r#"
+PREFIX nifi: <https://w3id.org/conn/nifi#>
+PREFIX fno: <https://w3id.org/function/ontology#>
+PREFIX fnom: <https://w3id.org/function/vocabulary/mapping#>
+PREFIX sh: <http://www.w3.org/ns/shacl#>
+PREFIX : <https://w3id.org/conn#> 
+
+# The serialization of the node itself takes precedence over the one of its channel
+SELECT ?class ?processor_id ?port_id ?key
+  (COALESCE(SAMPLE(?node_serialization), SAMPLE(?channel_serialization)) AS ?serialization)
+WHERE {
+    VALUES (?class ?end) { (:ReaderChannel :reader) (:WriterChannel :writer) }
+
+    ?processorTy a nifi:NifiProcess.
+
+    [] sh:targetClass ?processorTy;
+        sh:property [
+          sh:class ?class;
+          sh:path ?path;
+        ].
+
+     _:processor a ?processorTy;
+       <http://example.com/ns#testing+id> ?processor_id;
+       ?path ?subject.
+
+    ?subject <http://example.com/ns#testing+id> ?port_id.
+
+    # The relationship that feeds a writer
+    OPTIONAL {
+      ?processorTy nifi:mapping [
+        fno:parameterMapping [
+          fnom:functionParameter ?path;
+          fnom:implementationParameterPosition ?key;
+        ]
+      ].
+      FILTER(?class = :WriterChannel)
+    }
+
+    OPTIONAL { ?subject :serialization ?node_serialization }
+    OPTIONAL { [] ?end ?subject; :serialization ?channel_serialization }
+}
+GROUP BY ?class ?processor_id ?port_id ?key
+"#;
+
+    type Output = ChannelLinkOutput;
+}
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+
+use super::{Channel, ChannelConfig, ChannelContext, ChannelEndpoint, ChannelSide, ChannelStart};
+use crate::client::{Nifi, PortType};
+use crate::models::{Component, PortDTO};
+
+pub const PORT_READER: &str = "https://w3id.org/conn#PortReaderChannel";
+pub const PORT_WRITER: &str = "https://w3id.org/conn#PortWriterChannel";
+
+/// Ports of the process group of the pipeline. Flow files of the surrounding flow enter the
+/// pipeline through the input port of a reader and leave it through the output port of a writer
+pub struct PortChannel;
+
+#[async_trait::async_trait(?Send)]
+impl Channel for PortChannel {
+    fn ontology(&self) -> Option<(&str, &str)> {
+        Some((
+            "port_ontology.ttl",
+            include_str!("../../channels/port_ontology.ttl"),
+        ))
+    }
+
+    fn types(&self) -> &[&str] {
+        &[PORT_READER, PORT_WRITER]
+    }
+
+    async fn create(
+        &self,
+        ctx: &mut ChannelContext<'_>,
+        config: &ChannelConfig,
+    ) -> Option<ChannelEndpoint> {
+        let ty = match config.side {
+            ChannelSide::Reader => PortType::Input,
+            ChannelSide::Writer => PortType::Output,
+        };
+
+        let port = create_port(ctx.client, ty, &config.values).await?;
+        let pipeline = ctx.client.change_group(&ctx.client.group);
+
+        Some(ChannelEndpoint {
+            start: ChannelStart::Port(pipeline, ty, port.id.clone()),
+            port,
+        })
+    }
+}
+
+async fn create_port(
+    client: &Nifi,
+    ty: PortType,
+    values: &HashMap<String, String>,
+) -> Option<Component<PortDTO>> {
+    let name = match values.get("name") {
+        Some(x) => x,
+        None => {
+            eprintln!("Port channel without name");
+            return None;
+        }
+    };
+
+    println!("Creating {} port {}", ty, name);
+    match client.new_port(ty, name).await {
+        Ok(x) => Some(x.component),
+        Err(e) => {
+            eprintln!("Failed to create {} port {}\n{:?}", ty, name, e);
+            None
+        }
+    }
+}
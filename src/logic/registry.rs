@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use derive::Query;
+use oxigraph::model::NamedNode;
+
+use crate::models::PortEntity;
+use crate::sparql::{execute_query, Dataset, QueryField, QueryString, Queryable, Sol};
+
+/// How a channel type is created in nifi
+pub struct ChannelTemplate {
+    /// IRI of the nifi template that implements the channel, a local file or an embedded template
+    pub template: String,
+    /// Name of the port of the template that is linked to the processors
+    pub port: Option<String>,
+}
+
+impl ChannelTemplate {
+    /// The port with the configured name, or the first one
+    pub fn find_port<'a>(&self, ports: &'a [PortEntity]) -> Option<&'a PortEntity> {
+        match self.port {
+            Some(ref name) => ports.iter().find(|x| &x.component.comp.name == name),
+            None => ports.first(),
+        }
+    }
+}
+
+/// Channel types declared with `nifi:template` and `nifi:port` in the ontologies and inputs
+pub struct ChannelRegistry {
+    templates: HashMap<String, ChannelTemplate>,
+}
+
+impl ChannelRegistry {
+    pub fn load(dataset: &Dataset) -> Self {
+        let mut templates = HashMap::new();
+
+        for channel in execute_query::<ChannelTypeQuery>(dataset) {
+            let template = ChannelTemplate {
+                template: channel.template.as_str().to_string(),
+                port: channel.port.map(|x| x.0),
+            };
+            templates.insert(channel.ty.as_str().to_string(), template);
+        }
+
+        Self { templates }
+    }
+
+    pub fn get(&self, ty: &str) -> Option<&ChannelTemplate> {
+        self.templates.get(ty)
+    }
+}
+
+#[derive(Debug, Query)]
+struct ChannelTypeOutput {
+    pub ty: QueryField<NamedNode, "ty">,
+    pub template: QueryField<NamedNode, "template">,
+    pub port: Option<QueryString<"port">>,
+}
+
+struct ChannelTypeQuery;
+impl Queryable for ChannelTypeQuery {
+    const ERROR: &'static str = "Channel type query";
+    const QUERY: &'static str = r#"
+PREFIX nifi: <https://w3id.org/conn/nifi#>
+SELECT ?ty ?template ?port WHERE {
+    ?ty nifi:template ?template.
+    OPTIONAL { ?ty nifi:port ?port }
+}
+"#;
+    type Output = ChannelTypeOutput;
+}
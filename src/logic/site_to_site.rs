@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use oxigraph::model::NamedNode;
+
+use super::{
+    connect, create_connection, Channel, ChannelConfig, ChannelContext, ChannelEndpoint,
+    ChannelSide, ChannelStart,
+};
+use crate::client::{Nifi, PortType};
+use crate::models::{Component, ConnectionEntity, ConnectionTargetType, PortDTO};
+
+#[cfg(test)]
+mod tests;
+
+pub const SITE_TO_SITE_WRITER: &str = "https://w3id.org/conn#SiteToSiteWriterChannel";
+pub const SITE_TO_SITE_READER: &str = "https://w3id.org/conn#SiteToSiteReaderChannel";
+
+const DEFAULT_PROTOCOL: &str = "HTTP";
+/// Seconds to wait for nifi to find the public input port on the remote instance
+const DEFAULT_TIMEOUT: u64 = 10;
+
+/// Channels between nifi instances, a remote process group on the writer side and a public input
+/// port on the reader side
+pub struct SiteToSiteChannel;
+
+#[async_trait::async_trait(?Send)]
+impl Channel for SiteToSiteChannel {
+    fn ontology(&self) -> Option<(&str, &str)> {
+        Some((
+            "site_to_site_ontology.ttl",
+            include_str!("../../channels/site_to_site_ontology.ttl"),
+        ))
+    }
+
+    fn types(&self) -> &[&str] {
+        &[SITE_TO_SITE_READER, SITE_TO_SITE_WRITER]
+    }
+
+    async fn create(
+        &self,
+        ctx: &mut ChannelContext<'_>,
+        config: &ChannelConfig,
+    ) -> Option<ChannelEndpoint> {
+        let (group_client, port) = match config.side {
+            ChannelSide::Reader => create_reader(ctx.client, &config.values).await?,
+            ChannelSide::Writer => create_writer(ctx.client, &config.values).await?,
+        };
+
+        Some(ChannelEndpoint {
+            port,
+            start: ChannelStart::Group(group_client),
+        })
+    }
+}
+
+/// Creates a process group with an input port that sends the flow files through a remote process
+/// group to the public input port of another nifi instance
+async fn create_writer(
+    client: &Nifi,
+    values: &HashMap<String, String>,
+) -> Option<(Nifi, Component<PortDTO>)> {
+    let url = value(values, "url")?;
+    let port = value(values, "port")?;
+    let protocol = values
+        .get("transportProtocol")
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_PROTOCOL);
+    let timeout = timeout(values);
+
+    let group = client.new_process_group("SiteToSiteWriter").await.ok()?;
+    let group_client = client.change_group(&group.id);
+
+    match add_writer(&group_client, url, port, protocol, timeout).await {
+        Some(input) => Some((group_client, input)),
+        None => {
+            delete_group(&group_client).await;
+            None
+        }
+    }
+}
+
+async fn add_writer(
+    group_client: &Nifi,
+    url: &str,
+    port: &str,
+    protocol: &str,
+    timeout: Duration,
+) -> Option<Component<PortDTO>> {
+    let input = group_client
+        .new_port(PortType::Input, "fromRoot")
+        .await
+        .ok()?;
+
+    let remote = match group_client.new_remote_process_group(url, protocol).await {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Failed to create remote process group for {}\n{:?}", url, e);
+            return None;
+        }
+    };
+
+    let remote_port = match group_client
+        .wait_for_remote_port(&remote.id, port, timeout)
+        .await
+    {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("{}", e);
+            return None;
+        }
+    };
+
+    let connection = ConnectionEntity::new(&input.component, &remote_port, None);
+    create_connection(group_client, connection).await?;
+
+    Some(input.component)
+}
+
+/// Creates a process group with a public input port that other nifi instances send to, the flow
+/// files leave the group through its output port
+async fn create_reader(
+    client: &Nifi,
+    values: &HashMap<String, String>,
+) -> Option<(Nifi, Component<PortDTO>)> {
+    let port = value(values, "port")?;
+
+    let group = client.new_process_group("SiteToSiteReader").await.ok()?;
+    let group_client = client.change_group(&group.id);
+
+    match add_reader(&group_client, port).await {
+        Some(output) => Some((group_client, output)),
+        None => {
+            delete_group(&group_client).await;
+            None
+        }
+    }
+}
+
+async fn add_reader(group_client: &Nifi, port: &str) -> Option<Component<PortDTO>> {
+    let public = match group_client.new_public_port(port).await {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Failed to create public input port {}\n{:?}", port, e);
+            return None;
+        }
+    };
+
+    let output = group_client
+        .new_port(PortType::Output, "toRoot")
+        .await
+        .ok()?;
+
+    let connection = ConnectionEntity::new(&public.component, &output.component, None);
+    create_connection(group_client, connection).await?;
+
+    Some(output.component)
+}
+
+/// Removes the group of a channel that could not be created
+async fn delete_group(group_client: &Nifi) {
+    if let Err(e) = group_client.delete_process_group().await {
+        eprintln!(
+            "Failed to delete site-to-site group {}\n{:?}",
+            group_client.group, e
+        );
+    }
+}
+
+/// Connects components on two instances with a site-to-site channel, the public input port is
+/// named after the source, relationship and target. The flow files keep their attributes, so the
+/// source instance converts them to the serialization
+pub async fn link<I, O>(
+    source_client: &Nifi,
+    source: &Component<I>,
+    rel: Option<&str>,
+    target_client: &Nifi,
+    target: &Component<O>,
+    serialization: Option<&NamedNode>,
+    start: bool,
+) -> Option<()>
+where
+    for<'a> &'a I: Into<ConnectionTargetType>,
+    for<'a> &'a O: Into<ConnectionTargetType>,
+{
+    println!(
+        "Adding site-to-site link from {} to {}",
+        source_client.url(),
+        target_client.url()
+    );
+
+    let mut values = HashMap::new();
+    values.insert("port".to_string(), port_name(&source.id, rel, &target.id));
+
+    let (reader, output) = create_reader(target_client, &values).await?;
+
+    values.insert("url".to_string(), site_url(target_client.url()));
+    let (writer, input) = match create_writer(source_client, &values).await {
+        Some(x) => x,
+        None => {
+            // Nothing is connected to the reader yet
+            delete_group(&reader).await;
+            return None;
+        }
+    };
+
+    connect(target_client, &output, target, None, None, start).await?;
+    let serialization = serialization.map(|x| (x, ChannelSide::Writer));
+    connect(source_client, source, &input, rel, serialization, start).await?;
+
+    // The public input port has to accept flow files before the writer sends them
+    if start {
+        for client in [reader, writer] {
+            if let Err(e) = client.start_process_group().await {
+                eprintln!("Failed to start process group\n{:?}", e);
+            }
+        }
+    }
+
+    Some(())
+}
+
+/// Public input port names are unique per instance, a relationship can feed many targets
+fn port_name(source: &str, rel: Option<&str>, target: &str) -> String {
+    match rel {
+        Some(rel) => format!("{}-{}-{}", source, rel, target),
+        None => format!("{}-{}", source, target),
+    }
+}
+
+/// Site-to-site url of the instance behind a nifi-api url
+fn site_url(api: &str) -> String {
+    let api = api.trim_end_matches('/');
+    api.strip_suffix("-api").unwrap_or(api).to_string()
+}
+
+/// Time to wait for the remote port, `timeout` is in seconds
+fn timeout(values: &HashMap<String, String>) -> Duration {
+    let seconds = match values.get("timeout").map(|x| x.parse()) {
+        Some(Ok(x)) => x,
+        Some(Err(_)) => {
+            eprintln!(
+                "Invalid site-to-site timeout {}, using {} s",
+                values["timeout"], DEFAULT_TIMEOUT
+            );
+            DEFAULT_TIMEOUT
+        }
+        None => DEFAULT_TIMEOUT,
+    };
+    Duration::from_secs(seconds)
+}
+
+fn value<'a>(values: &'a HashMap<String, String>, key: &str) -> Option<&'a str> {
+    match values.get(key) {
+        Some(x) => Some(x),
+        None => {
+            eprintln!("Site-to-site channel without {}", key);
+            None
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::{port_name, site_url, timeout};
+
+#[test]
+fn site_urls_drop_the_api_suffix() {
+    assert_eq!(
+        site_url("http://transform:8080/nifi-api"),
+        "http://transform:8080/nifi"
+    );
+    assert_eq!(
+        site_url("http://transform:8080/nifi-api/"),
+        "http://transform:8080/nifi"
+    );
+    assert_eq!(
+        site_url("http://transform:8080/nifi"),
+        "http://transform:8080/nifi"
+    );
+}
+
+#[test]
+fn fan_out_gets_a_port_per_target() {
+    assert_ne!(
+        port_name("source", Some("success"), "a"),
+        port_name("source", Some("success"), "b")
+    );
+    assert_ne!(
+        port_name("source", Some("success"), "a"),
+        port_name("source", Some("failure"), "a")
+    );
+}
+
+#[test]
+fn remote_ports_are_awaited_for_the_timeout() {
+    let values = |timeout: &str| HashMap::from([("timeout".to_string(), timeout.to_string())]);
+
+    assert_eq!(timeout(&HashMap::new()), Duration::from_secs(10));
+    assert_eq!(timeout(&values("30")), Duration::from_secs(30));
+    assert_eq!(timeout(&values("soon")), Duration::from_secs(10));
+}
@@ -0,0 +1,326 @@
+use std::collections::HashSet;
+
+use oxigraph::io::GraphFormat;
+use oxigraph::model::{GraphName, NamedNode, NamedNodeRef, Term};
+use oxigraph::store::Store;
+use serde_json::{json, Value};
+
+use crate::client::Nifi;
+use crate::models::ProcessorEntity;
+use crate::rdf::{to_turtle, Serialization};
+use crate::sparql::{
+    get_parameter_solutions, materialize_rdfs, Dataset, NifiChannelLinkOutput, ProcessorQuery,
+    QueryField, QuerySolutionOutput,
+};
+
+use super::{
+    assign_versions, check_converters, content_type, convert_arguments, funnel_parts,
+    pipeline_dataset, planned_processors, processor_properties, select_bundle, typed_subjects,
+    ChannelSide, Instances, Part, INFERRED_GRAPH, ONTOLOGY_GRAPH,
+};
+
+const INPUT_GRAPH: &str = "http://example.com/input";
+
+const PROCESSOR: &str = r#"
+@prefix nifi: <https://w3id.org/conn/nifi#> .
+
+<http://example.com/processor> a nifi:org.apache.nifi.processors.standard.LogMessage"#;
+
+/// The log message processor of a bundle version, with extra properties
+fn log_message(version: &str, properties: &[&str]) -> ProcessorEntity {
+    let fixture = include_str!("../../tests/fixtures/log_message.json");
+    let mut entity: Value = serde_json::from_str(fixture).unwrap();
+
+    let component = &mut entity["component"];
+    component["bundle"]["version"] = json!(version);
+    for name in properties {
+        component["config"]["descriptors"][name] = json!({
+            "name": name,
+            "displayName": name,
+            "description": "",
+            "required": false,
+        });
+    }
+
+    serde_json::from_value(entity).unwrap()
+}
+
+/// Loads the generated ontologies and the input in their graphs
+fn load_store(ontologies: &[ProcessorEntity], input: &str) -> Store {
+    let store = Store::new().unwrap();
+    let ontology_graph = NamedNodeRef::new(ONTOLOGY_GRAPH).unwrap();
+    for entity in ontologies {
+        let turtle = to_turtle(entity).unwrap();
+        store
+            .load_graph(turtle.as_slice(), GraphFormat::Turtle, ontology_graph, None)
+            .unwrap();
+    }
+    let input_graph = NamedNodeRef::new(INPUT_GRAPH).unwrap();
+    store
+        .load_graph(input.as_bytes(), GraphFormat::Turtle, input_graph, None)
+        .unwrap();
+    store
+}
+
+/// Loads the generated ontologies and the input like a deploy does, and returns the solution of
+/// the only processor
+fn deployed_solution(ontologies: &[ProcessorEntity], input: &str) -> Vec<QuerySolutionOutput> {
+    let store = load_store(ontologies, input);
+
+    let graphs = [INPUT_GRAPH, ONTOLOGY_GRAPH, INFERRED_GRAPH]
+        .map(|x| GraphName::from(NamedNode::new_unchecked(x)))
+        .to_vec();
+    let dataset = Dataset {
+        store: &store,
+        graphs,
+    };
+
+    materialize_rdfs(&dataset, INFERRED_GRAPH);
+    assign_versions(&dataset);
+
+    let solutions = get_parameter_solutions::<ProcessorQuery>(&dataset);
+    assert_eq!(solutions.len(), 1);
+
+    solutions.into_values().next().unwrap()
+}
+
+/// The nifi properties of the only processor
+fn deployed_properties(ontologies: &[ProcessorEntity], input: &str) -> Vec<(String, String)> {
+    let solution = deployed_solution(ontologies, input);
+    let mut properties = processor_properties(&solution).unwrap();
+    properties.sort();
+    properties
+}
+
+fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+    expected
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn properties_keep_their_nifi_key() {
+    let ontology = log_message("1.18.0", &["Remote URL", "a.b/c", "SSL2", "-a."]);
+    let input = format!(
+        r#"{};
+  nifi:Remote%20URL "http://localhost:8080";
+  nifi:a.b%2Fc "slash";
+  nifi:SSL2 "two";
+  nifi:%2Da%2E "dots"."#,
+        PROCESSOR
+    );
+
+    let expected = pairs(&[
+        ("-a.", "dots"),
+        ("Remote URL", "http://localhost:8080"),
+        ("SSL2", "two"),
+        ("a.b/c", "slash"),
+    ]);
+    assert_eq!(deployed_properties(&[ontology], &input), expected);
+}
+
+#[test]
+fn legacy_names_still_deploy() {
+    let ontology = log_message("1.18.0", &["Remote URL"]);
+    let input = r#"
+@prefix nifi: <https://w3id.org/conn/nifi#> .
+
+<http://example.com/processor> a nifi:LogMessage;
+  nifi:remote-url "http://localhost:8080";
+  nifi:log-level "info"."#;
+
+    let expected = pairs(&[
+        ("Remote URL", "http://localhost:8080"),
+        ("log-level", "info"),
+    ]);
+    assert_eq!(deployed_properties(&[ontology], input), expected);
+}
+
+#[test]
+fn conversions_need_a_converter() {
+    let input = format!(
+        r#"@prefix : <https://w3id.org/conn#> .
+{};
+  nifi:success _:writer.
+
+[] a nifi:NifiChannel;
+  :writer _:writer;
+  :reader [];
+  :serialization :turtle."#,
+        PROCESSOR
+    );
+
+    let store = load_store(&[log_message("1.18.0", &[])], &input);
+    let documents = [GraphName::from(NamedNode::new_unchecked(INPUT_GRAPH))];
+    let dataset = pipeline_dataset(&store, &documents, None);
+    let processors = planned_processors(&dataset, None).unwrap();
+
+    let client = |converter| Nifi::new("http://localhost:8091/nifi-api", "root", converter, None);
+    let instances = Instances::load(&dataset, &client(None).unwrap()).unwrap();
+    let error = check_converters(&dataset, &instances, &processors).unwrap_err();
+    assert!(error.contains("http://example.com/processor"), "{}", error);
+
+    let converter = client(Some("/opt/nifi-runner")).unwrap();
+    let instances = Instances::load(&dataset, &converter).unwrap();
+    assert!(check_converters(&dataset, &instances, &processors).is_ok());
+}
+
+#[test]
+fn properties_need_a_literal() {
+    let ontology = log_message("1.18.0", &[]);
+    let input = format!(
+        "{};\n  nifi:log-level <http://example.com/info>.",
+        PROCESSOR
+    );
+
+    let solution = deployed_solution(&[ontology], &input);
+    let error = processor_properties(&solution).unwrap_err();
+    assert!(error.contains("http://example.com/processor"), "{}", error);
+}
+
+#[test]
+fn processors_use_one_bundle_version() {
+    let ontologies = [
+        log_message("1.18.0", &["Old"]),
+        log_message("1.19.0", &["New"]),
+    ];
+    let input = |pin: &str| {
+        format!(
+            r#"{};
+  {}
+  nifi:Old "old";
+  nifi:New "new";
+  nifi:log-level "info"."#,
+            PROCESSOR, pin
+        )
+    };
+
+    // The most recent version, without the properties of the other one
+    let expected = pairs(&[("New", "new"), ("log-level", "info")]);
+    assert_eq!(deployed_properties(&ontologies, &input("")), expected);
+
+    let pinned = input(r#"nifi:bundleVersion "1.18.0";"#);
+    let expected = pairs(&[("Old", "old"), ("log-level", "info")]);
+    assert_eq!(deployed_properties(&ontologies, &pinned), expected);
+
+    let bundle = select_bundle(&deployed_solution(&ontologies, &pinned)).unwrap();
+    assert_eq!(bundle.map(|x| x.version), Some("1.18.0".to_string()));
+
+    // A pin without a bundle fails instead of letting nifi choose
+    let missing = input(r#"nifi:bundleVersion "1.17.0";"#);
+    assert!(select_bundle(&deployed_solution(&ontologies, &missing)).is_err());
+}
+
+#[test]
+fn pipelines_own_the_processors_typed_in_their_graph() {
+    let input = r#"
+@prefix nifi: <https://w3id.org/conn/nifi#> .
+
+<http://example.com/shared> a nifi:org.apache.nifi.processors.standard.LogMessage.
+<http://example.com/pipeline> {
+    <http://example.com/own> a nifi:org.apache.nifi.processors.standard.LogMessage.
+    <http://example.com/shared> nifi:log-level "info".
+}
+"#;
+
+    let store = Store::new().unwrap();
+    store
+        .load_dataset(input.as_bytes(), oxigraph::io::DatasetFormat::TriG, None)
+        .unwrap();
+
+    let pipeline = NamedNode::new_unchecked("http://example.com/pipeline");
+    let own = Term::from(NamedNode::new_unchecked("http://example.com/own"));
+    assert_eq!(typed_subjects(&store, &pipeline), HashSet::from([own]));
+}
+
+#[test]
+fn serializations_have_a_content_type() {
+    let serialization = |local: &str| {
+        content_type(&NamedNode::new(format!("https://w3id.org/conn#{}", local)).unwrap())
+    };
+
+    assert_eq!(serialization("turtle"), Some(Serialization::Turtle));
+    assert_eq!(serialization("nquads"), Some(Serialization::NQuads));
+    assert_eq!(serialization("jsonld"), Some(Serialization::JsonLd));
+    assert_eq!(serialization("none"), None);
+    assert_eq!(serialization("rdfxml"), None);
+}
+
+#[test]
+fn writers_convert_and_readers_parse() {
+    assert_eq!(
+        convert_arguments(Serialization::JsonLd, ChannelSide::Writer),
+        "convert;--from=${mime.type};--to=application/ld+json"
+    );
+    assert_eq!(
+        convert_arguments(Serialization::NQuads, ChannelSide::Reader),
+        "convert;--to=application/n-quads"
+    );
+}
+
+/// The processors of a pipeline on an instance, the processors only differ in id
+fn part(instance: usize, ids: &[&str]) -> Part {
+    let procs = ids
+        .iter()
+        .map(|id| (id.to_string(), log_message("1.18.0", &[]).component))
+        .collect();
+
+    Part {
+        instance,
+        client: Nifi::new("http://localhost:8091/nifi-api", "root", None, None).unwrap(),
+        procs,
+    }
+}
+
+/// A channel that links every writer to every reader
+fn channel(writers: &[&str], readers: &[&str]) -> Vec<NifiChannelLinkOutput> {
+    let channel: Term = NamedNode::new("http://example.com/channel").unwrap().into();
+
+    let mut links = Vec::new();
+    for writer in writers {
+        for reader in readers {
+            links.push(NifiChannelLinkOutput {
+                channel: QueryField(channel.clone()),
+                source_id: QueryField(writer.to_string()),
+                target_id: QueryField(reader.to_string()),
+                key: QueryField("success".to_string()),
+                serialization: None,
+            });
+        }
+    }
+    links
+}
+
+#[test]
+fn funnels_only_when_they_save_connections() {
+    let parts = [part(0, &["w1", "w2", "r1", "r2", "r3"])];
+    let instances = |links: &[NifiChannelLinkOutput]| {
+        funnel_parts(&parts, links).map(|x| x.iter().map(|x| x.instance).collect::<Vec<_>>())
+    };
+
+    assert_eq!(instances(&channel(&["w1"], &["r1"])), None);
+    assert_eq!(instances(&channel(&["w1", "w2"], &["r1", "r2"])), None);
+    assert_eq!(
+        instances(&channel(&["w1", "w2"], &["r1", "r2", "r3"])),
+        Some(vec![0])
+    );
+}
+
+#[test]
+fn funnels_span_instances() {
+    let parts = [
+        part(0, &["w1", "r1"]),
+        part(1, &["w2", "r2"]),
+        part(2, &["r3"]),
+        part(3, &["unused"]),
+    ];
+
+    let links = channel(&["w1", "w2"], &["r1", "r2", "r3"]);
+    let used = funnel_parts(&parts, &links).unwrap();
+    let instances: Vec<_> = used.iter().map(|x| x.instance).collect();
+    assert_eq!(instances, vec![0, 1, 2]);
+
+    let links = channel(&["w1", "w2"], &["r1", "r2", "missing"]);
+    assert!(funnel_parts(&parts, &links).is_none());
+}
@@ -1,29 +1,17 @@
-#![feature(adt_const_params)]
-use crate::models::DocumentedTypeDTO;
 use app::{Actives, ListAction, Output, RunArgs};
+use cache::{default_cache_dir, OntologyCache};
 use clap::Parser;
 use client::Nifi;
-use oxiri::Iri;
-use rdf::{RdfContext, ToRDF};
-use rio_turtle::{TurtleFormatter, TurtleParser};
+use models::DocumentedTypeDTO;
+use nifi_runner::{app, cache, channels, client, export, logic, models, rdf};
+use oxigraph::io::GraphFormat;
+use rdf::{to_format, to_jsonld, to_turtle, type_iri, ToRDF};
 use serde::Serialize;
-use std::io::Write;
-use std::{
-    fmt::Debug,
-    io::{stdout, Cursor},
-};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::{fmt::Debug, io::stdout};
 
-use crate::app::Args;
-
-pub mod app;
-pub mod client;
-pub mod logic;
-pub mod models;
-pub mod rdf;
-mod sparql;
-mod util;
-
-const BASE_URI: &str = "http://example.com/ns#";
+use app::Args;
 
 fn print_result<T: Serialize, E: Debug>(r: Result<T, E>) -> Result<T, E> {
     match r {
@@ -34,39 +22,21 @@ fn print_result<T: Serialize, E: Debug>(r: Result<T, E>) -> Result<T, E> {
     r
 }
 
-fn format_output<T: Serialize + ToRDF>(item: T, args: RunArgs) {
-    let base = args.base.as_ref().map(|x| x.as_str()).unwrap_or(BASE_URI);
-    match args.output {
+/// Prints the item in the requested format, items that can't be written in it give an error
+fn format_output<T: Serialize + ToRDF>(item: T, args: RunArgs) -> io::Result<()> {
+    let out = match args.output {
         Output::Json => {
-            let out = serde_json::to_string_pretty(&item)
-                .unwrap_or_else(|_| String::from("Failed to serialize"));
-            println!("{}", out);
+            let mut out = serde_json::to_vec_pretty(&item)?;
+            out.push(b'\n');
+            out
         }
-        Output::Turtle => {
-            let mut cursor = Cursor::new(Vec::new());
-            let mut ctx = RdfContext::default();
-            <T>::add_ctx(&mut ctx);
-            let prefixes = ctx.prefixes();
-
-            cursor.write_all(prefixes.as_bytes()).unwrap();
-            item.to_rdf(&mut cursor).unwrap();
-            cursor.set_position(0);
-
-            std::io::copy(&mut cursor, &mut stdout().lock()).unwrap();
+        Output::Turtle => to_turtle(item)?,
+        Output::NTriples => to_format(item, GraphFormat::NTriples)?,
+        Output::JsonLd => to_jsonld(item)?,
+        Output::RdfXml => to_format(item, GraphFormat::RdfXml)?,
+    };
 
-            // use rio_api::formatter::TriplesFormatter;
-            // use rio_api::parser::TriplesParser;
-            // let mut turtle_fmt = TurtleFormatter::new(stdout());
-            //
-            // let mut turtle_parser =
-            //     TurtleParser::new(cursor, Some(Iri::parse(base.to_string()).unwrap()));
-            // turtle_parser
-            //     .parse_all(&mut |x| turtle_fmt.format(&x))
-            //     .unwrap();
-            //
-            // turtle_fmt.finish().unwrap().flush().unwrap();
-        }
-    }
+    stdout().lock().write_all(&out)
 }
 
 fn filter_list(
@@ -101,41 +71,59 @@ async fn handle_list_action(
     client: Nifi,
     action: ListAction,
     output: RunArgs,
+    cache_dir: PathBuf,
 ) -> Result<(), Box<dyn std::error::Error>> {
     match action {
         ListAction::Types { filter, full } => {
             let types = client.list_types().await?;
             let types = filter_list(types.types, filter);
             if full {
+                let mut cache = OntologyCache::open(cache_dir, &client.nifi_version().await?)?;
                 let mut out = Vec::new();
                 for p in types {
-                    let processor = client.new_processor(&p.ty).await?;
-                    client.delete_processor(&processor.id, 1).await?;
+                    let processor = client.describe_processor(&p).await?;
+                    if let Some(ref bundle) = p.bundle {
+                        cache.store(bundle, &type_iri(&p.ty), &to_turtle(&processor)?)?;
+                    }
                     out.push(processor);
                 }
-                format_output(&out, output);
+                format_output(&out, output)?;
             } else {
-                format_output(&types, output);
+                format_output(&types, output)?;
             }
         }
         ListAction::Services { filter } => {
             let types = client.list_services_types().await?;
             let types = filter_list(types.types, filter);
-            format_output(&types, output);
+            format_output(&types, output)?;
         }
         ListAction::Type { ty } => {
-            let processor = client.new_processor(&ty).await?;
-
-            format_output(&processor, output);
-
-            client.delete_processor(&processor.id, 1).await?;
+            let ty = client
+                .list_types()
+                .await?
+                .types
+                .into_iter()
+                .find(|x| x.ty == ty)
+                .ok_or_else(|| format!("Unknown processor type {}", ty))?;
+
+            let processor = client.describe_processor(&ty).await?;
+            if let Some(ref bundle) = ty.bundle {
+                let mut cache = OntologyCache::open(cache_dir, &client.nifi_version().await?)?;
+                cache.store(bundle, &type_iri(&ty.ty), &to_turtle(&processor)?)?;
+            }
+            format_output(&processor, output)?;
         }
         ListAction::Service { ty } => {
-            let processor = client.new_service(&ty).await?;
-
-            format_output(&processor, output);
-
-            client.delete_service(&processor.id, 1).await?;
+            let ty = client
+                .list_services_types()
+                .await?
+                .types
+                .into_iter()
+                .find(|x| x.ty == ty)
+                .ok_or_else(|| format!("Unknown service type {}", ty))?;
+
+            let service = client.describe_service(&ty).await?;
+            format_output(&service, output)?;
         }
         ListAction::Active {
             ty: Actives::Service,
@@ -161,22 +149,59 @@ async fn handle_list_action(
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
+    let cache_dir = args.cache.unwrap_or_else(default_cache_dir);
+    let client = args.client.authorize()?;
 
     match args.action {
         app::Action::Run {
             ontology,
             input,
+            input_format,
+            keep_going,
+            pipeline,
+            channels_dir,
             no_start,
         } => {
-            logic::startup(args.client, ontology, input, !no_start).await;
+            let input = logic::InputOptions {
+                locations: input,
+                parse: logic::ParseOptions {
+                    format: input_format,
+                    base: args.run.base,
+                    keep_going,
+                },
+            };
+            logic::startup(
+                client,
+                ontology,
+                cache_dir,
+                channels::channel_dirs(channels_dir),
+                &logic::ChannelPlugins::default(),
+                input,
+                pipeline,
+                !no_start,
+            )
+            .await?;
+        }
+        app::Action::Convert { from, to } => {
+            let to = rdf::Serialization::from_mime(&to)
+                .ok_or_else(|| format!("Unknown content type {}", to))?;
+            let from = from
+                .as_deref()
+                .and_then(rdf::Serialization::from_mime)
+                .unwrap_or(to);
+            rdf::convert(std::io::stdin().lock(), from, to, &mut stdout().lock())?;
+        }
+        app::Action::Export => {
+            let flow = export::export_flow(&client).await?;
+            format_output(&flow, args.run)?;
         }
         app::Action::Info => {
-            print_result(args.client.get_info().await)?;
+            print_result(client.get_info().await)?;
         }
         app::Action::List { action } => {
-            handle_list_action(args.client, action, args.run).await?;
+            handle_list_action(client, action, args.run, cache_dir).await?;
         }
-        app::Action::Testing => handle_testing(args.client, args.run).await?,
+        app::Action::Testing => handle_testing(client, args.run).await?,
     }
 
     Ok(())
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use serde::Deserialize;
 use serde::Serialize;
@@ -41,10 +41,18 @@ pub struct ProcessTypesEntity {
     pub types: Vec<DocumentedTypeDTO>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct BundleDTO {
+    pub group: String,
+    pub artifact: String,
+    pub version: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DocumentedTypeDTO {
     #[serde(rename = "type")]
     pub ty: String,
+    pub bundle: Option<BundleDTO>,
     pub description: Option<String>,
     #[serde(rename = "controllerServiceApis")]
     pub apis: Option<Vec<ControllerServiceApiDTO>>,
@@ -57,10 +65,10 @@ pub struct ControllerServicesEntity {
     pub services: Vec<ControllerServiceEntity>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ControllerServiceApiDTO {
     #[serde(rename = "type")]
-    ty: String,
+    pub ty: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -68,8 +76,11 @@ pub struct ControllerServiceDTO {
     pub name: String,
     #[serde(rename = "type")]
     pub ty: String,
+    pub bundle: Option<BundleDTO>,
     pub description: Option<String>,
-    pub descriptors: HashMap<String, DescriptorDTO>,
+    pub descriptors: BTreeMap<String, DescriptorDTO>,
+    #[serde(rename = "controllerServiceApis")]
+    pub apis: Option<Vec<ControllerServiceApiDTO>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -94,6 +105,9 @@ pub type ProcessorEntity = VersionedEntity<ProcessorDTO, StatusDTO<ProcessRunSta
 pub type ControllerServiceEntity =
     VersionedEntity<ControllerServiceDTO, StatusDTO<ServiceRunStatus>>;
 pub type PortEntity = VersionedEntity<PortDTO, Value>;
+pub type RemoteProcessGroupEntity = VersionedEntity<RemoteProcessGroupDTO, Value>;
+/// Funnels have no status
+pub type FunnelEntity = VersionedEntity<FunnelDTO, Option<Value>>;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RevisionDTO {
@@ -104,6 +118,7 @@ pub struct RevisionDTO {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PortDTO {
+    pub name: String,
     #[serde(rename = "type")]
     ty: String,
 }
@@ -113,6 +128,7 @@ pub struct ProcessorDTO {
     pub name: String,
     #[serde(rename = "type")]
     pub ty: String,
+    pub bundle: Option<BundleDTO>,
     pub description: Option<String>,
 
     pub relationships: Vec<RelationshipDTO>,
@@ -127,8 +143,16 @@ pub struct RelationshipDTO {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProcessorConfigDTO {
-    pub properties: HashMap<String, Option<String>>,
-    pub descriptors: HashMap<String, DescriptorDTO>,
+    pub properties: BTreeMap<String, Option<String>>,
+    pub descriptors: BTreeMap<String, DescriptorDTO>,
+    #[serde(rename = "schedulingPeriod", skip_serializing_if = "Option::is_none")]
+    pub scheduling_period: Option<String>,
+    /// Relationships that drop their flow files instead of being connected
+    #[serde(
+        rename = "autoTerminatedRelationships",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub auto_terminated: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -140,14 +164,188 @@ pub struct DescriptorDTO {
     #[serde(rename = "defaultValue")]
     pub default_value: Option<String>,
     pub required: bool,
+    #[serde(rename = "allowableValues")]
+    pub allowable_values: Option<Vec<AllowableValueEntity>>,
+    #[serde(default)]
+    pub sensitive: bool,
+    #[serde(rename = "expressionLanguageScope")]
+    pub expression_language_scope: Option<String>,
+    /// Controller service API this property references
+    #[serde(rename = "identifiesControllerService")]
+    pub identifies_controller_service: Option<String>,
+    #[serde(rename = "identifiesControllerServiceBundle")]
+    pub identifies_controller_service_bundle: Option<BundleDTO>,
+    pub dependencies: Option<Vec<PropertyDependencyDTO>>,
+    /// Only known when the descriptor comes from a processor definition
+    pub validator: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllowableValueEntity {
+    #[serde(rename = "allowableValue")]
+    pub allowable_value: AllowableValueDTO,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllowableValueDTO {
+    #[serde(rename = "displayName")]
+    pub display: Option<String>,
+    pub value: String,
+    pub description: Option<String>,
+}
+
+/// This property is only relevant when `property_name` has one of the `dependent_values`,
+/// or any value when none are given
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PropertyDependencyDTO {
+    #[serde(rename = "propertyName")]
+    pub property_name: String,
+    #[serde(rename = "dependentValues")]
+    pub dependent_values: Option<Vec<String>>,
+}
+
+/// Read-only description of a processor type, as returned by `/flow/processor-definition`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessorDefinition {
+    #[serde(rename = "type")]
+    pub ty: String,
+    #[serde(flatten)]
+    pub bundle: BundleDTO,
+    pub type_description: Option<String>,
+    #[serde(default)]
+    pub property_descriptors: BTreeMap<String, PropertyDescriptor>,
+    #[serde(default)]
+    pub supported_relationships: Vec<RelationshipDTO>,
+}
+
+/// Read-only description of a controller service type, as returned by
+/// `/flow/controller-service-definition`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ControllerServiceDefinition {
+    #[serde(rename = "type")]
+    pub ty: String,
+    #[serde(flatten)]
+    pub bundle: BundleDTO,
+    pub type_description: Option<String>,
+    #[serde(default)]
+    pub property_descriptors: BTreeMap<String, PropertyDescriptor>,
+    #[serde(default)]
+    pub provided_api_implementations: Vec<DefinedType>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyDescriptor {
+    pub name: String,
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+    pub default_value: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    pub allowable_values: Option<Vec<AllowableValueDTO>>,
+    #[serde(default)]
+    pub sensitive: bool,
+    pub expression_language_scope: Option<String>,
+    pub type_provided_by_value: Option<DefinedType>,
+    pub dependencies: Option<Vec<PropertyDependencyDTO>>,
+    pub validator: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DefinedType {
+    #[serde(rename = "type")]
+    pub ty: String,
+    #[serde(flatten)]
+    pub bundle: Option<BundleDTO>,
+}
+
+impl From<PropertyDescriptor> for DescriptorDTO {
+    fn from(this: PropertyDescriptor) -> Self {
+        let (service, service_bundle) = match this.type_provided_by_value {
+            Some(x) => (Some(x.ty), x.bundle),
+            None => (None, None),
+        };
+
+        DescriptorDTO {
+            display: this.display_name.unwrap_or_else(|| this.name.clone()),
+            name: this.name,
+            description: this.description.unwrap_or_default(),
+            default_value: this.default_value,
+            required: this.required,
+            allowable_values: this.allowable_values.map(|xs| {
+                xs.into_iter()
+                    .map(|allowable_value| AllowableValueEntity { allowable_value })
+                    .collect()
+            }),
+            sensitive: this.sensitive,
+            expression_language_scope: this.expression_language_scope,
+            identifies_controller_service: service,
+            identifies_controller_service_bundle: service_bundle,
+            dependencies: this.dependencies,
+            validator: this.validator,
+        }
+    }
+}
+
+impl From<ProcessorDefinition> for ProcessorDTO {
+    fn from(this: ProcessorDefinition) -> Self {
+        let properties = this
+            .property_descriptors
+            .iter()
+            .map(|(k, v)| (k.clone(), v.default_value.clone()))
+            .collect();
+        let descriptors = this
+            .property_descriptors
+            .into_iter()
+            .map(|(k, v)| (k, v.into()))
+            .collect();
+
+        ProcessorDTO {
+            name: crate::rdf::type_name(&this.ty).to_string(),
+            ty: this.ty,
+            bundle: Some(this.bundle),
+            description: this.type_description,
+            relationships: this.supported_relationships,
+            config: ProcessorConfigDTO {
+                properties,
+                descriptors,
+                scheduling_period: None,
+                auto_terminated: None,
+            },
+        }
+    }
+}
+
+impl From<ControllerServiceDefinition> for ControllerServiceDTO {
+    fn from(this: ControllerServiceDefinition) -> Self {
+        ControllerServiceDTO {
+            name: crate::rdf::type_name(&this.ty).to_string(),
+            ty: this.ty,
+            bundle: Some(this.bundle),
+            description: this.type_description,
+            descriptors: this
+                .property_descriptors
+                .into_iter()
+                .map(|(k, v)| (k, v.into()))
+                .collect(),
+            apis: Some(
+                this.provided_api_implementations
+                    .into_iter()
+                    .map(|x| ControllerServiceApiDTO { ty: x.ty })
+                    .collect(),
+            ),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ConnectionEntity {
-    id: Option<String>,
-    revision: RevisionDTO,
-    component: ConnectionDTO,
+    pub id: Option<String>,
+    pub revision: RevisionDTO,
+    pub component: ConnectionDTO,
 }
 
 impl ConnectionEntity {
@@ -185,7 +383,7 @@ impl ConnectionEntity {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum ConnectionTargetType {
     Processor,
@@ -206,6 +404,18 @@ impl<'a> From<&'a PortDTO> for ConnectionTargetType {
     }
 }
 
+impl<'a> From<&'a RemotePortDTO> for ConnectionTargetType {
+    fn from(this: &'a RemotePortDTO) -> Self {
+        this.ty
+    }
+}
+
+impl<'a> From<&'a FunnelDTO> for ConnectionTargetType {
+    fn from(_: &'a FunnelDTO) -> Self {
+        Self::Funnel
+    }
+}
+
 impl<'a> From<&'a ProcessorDTO> for ConnectionTargetType {
     fn from(_: &'a ProcessorDTO) -> Self {
         Self::Processor
@@ -215,24 +425,93 @@ impl<'a> From<&'a ProcessorDTO> for ConnectionTargetType {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ConnectionDTO {
-    source: ConnectableDTO,
-    destination: ConnectableDTO,
-    selected_relationships: Vec<String>,
-    available_relationships: Vec<String>,
+    pub source: ConnectableDTO,
+    pub destination: ConnectableDTO,
+    #[serde(default)]
+    pub selected_relationships: Vec<String>,
+    #[serde(default)]
+    pub available_relationships: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ConnectableDTO {
-    id: String,
+    pub id: String,
     #[serde(rename = "type")]
-    ty: ConnectionTargetType,
-    group_id: String,
+    pub ty: ConnectionTargetType,
+    pub group_id: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FunnelDTO {}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProcessGroupDTO {
-    name: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteProcessGroupDTO {
+    pub target_uris: String,
+    pub transport_protocol: String,
+    /// Only known after nifi fetched the ports of the remote instance
+    pub contents: Option<RemoteProcessGroupContentsDTO>,
+}
+
+impl RemoteProcessGroupDTO {
+    /// The input port with this name of the remote instance
+    pub fn input_port(&self, name: &str) -> Option<Component<RemotePortDTO>> {
+        let ports = &self.contents.as_ref()?.input_ports;
+        remote_port(ports, name, ConnectionTargetType::RemoteInputPort)
+    }
+
+    /// The output port with this name of the remote instance
+    pub fn output_port(&self, name: &str) -> Option<Component<RemotePortDTO>> {
+        let ports = &self.contents.as_ref()?.output_ports;
+        remote_port(ports, name, ConnectionTargetType::RemoteOutputPort)
+    }
+}
+
+fn remote_port(
+    ports: &[RemoteProcessGroupPortDTO],
+    name: &str,
+    ty: ConnectionTargetType,
+) -> Option<Component<RemotePortDTO>> {
+    let port = ports.iter().find(|x| x.name == name)?;
+    Some(Component {
+        id: port.id.clone(),
+        parent_group_id: port.group_id.clone(),
+        comp: RemotePortDTO {
+            name: port.name.clone(),
+            ty,
+        },
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteProcessGroupContentsDTO {
+    #[serde(default)]
+    pub input_ports: Vec<RemoteProcessGroupPortDTO>,
+    #[serde(default)]
+    pub output_ports: Vec<RemoteProcessGroupPortDTO>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteProcessGroupPortDTO {
+    pub id: String,
+    /// Id of the remote process group
+    pub group_id: String,
+    pub name: String,
+}
+
+/// A port of a remote instance, as seen from a remote process group
+#[derive(Debug, Clone)]
+pub struct RemotePortDTO {
+    pub name: String,
+    ty: ConnectionTargetType,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -250,6 +529,8 @@ pub struct Flow {
     pub input_ports: Vec<PortEntity>,
     pub output_ports: Vec<PortEntity>,
     pub connections: Vec<ConnectionEntity>,
+    #[serde(default)]
+    pub remote_process_groups: Vec<RemoteProcessGroupEntity>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -257,6 +538,18 @@ pub struct FlowEntity {
     pub flow: Flow,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProcessGroupFlowEntity {
+    #[serde(rename = "processGroupFlow")]
+    pub group_flow: ProcessGroupFlowDTO,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProcessGroupFlowDTO {
+    pub id: String,
+    pub flow: Flow,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VariableRegistryEntity {
     #[serde(rename = "processGroupRevision")]
@@ -1,17 +1,31 @@
-use std::{collections::HashSet, io::Write};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
+use oxigraph::io::{GraphFormat, GraphSerializer};
+use oxigraph::model::{BlankNode, Literal, NamedNode, Subject, Term, Triple};
+
+use crate::datatype::{self, Datatype};
 use crate::models::{
-    ControllerServiceDTO, DescriptorDTO, DocumentedTypeDTO, ProcessorDTO, RelationshipDTO,
-    VersionedEntity,
+    BundleDTO, ControllerServiceDTO, DescriptorDTO, DocumentedTypeDTO, ProcessorDTO,
+    RelationshipDTO, VersionedEntity,
 };
 
 use self::prefix::Prefix;
 
+mod convert;
+mod jsonld;
+#[cfg(test)]
+mod tests;
+mod turtle;
+pub use convert::{convert, Serialization};
+pub use jsonld::{read_jsonld, write_jsonld};
+pub use turtle::write_turtle;
+
 pub mod prefix {
     pub type Prefix = (&'static str, &'static str);
     pub static SH: Prefix = ("sh", "http://www.w3.org/ns/shacl#");
     pub static XSD: Prefix = ("xsd", "http://www.w3.org/2001/XMLSchema#");
     pub static DCTERMS: Prefix = ("dcterms", "http://purl.org/dc/terms/");
+    pub static RDF: Prefix = ("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#");
     pub static RDFS: Prefix = ("rdfs", "http://www.w3.org/2000/01/rdf-schema#");
     pub static SDS: Prefix = ("sds", "https://w3id.org/sds#");
     pub static CONN: Prefix = ("", "https://w3id.org/conn#");
@@ -23,26 +37,168 @@ use prefix::*;
 
 #[derive(Default, Debug)]
 pub struct RdfContext {
-    prefixes: HashSet<Prefix>,
-}
-
-fn prefix_to_string(prefix: &Prefix) -> String {
-    format!("@prefix {}: <{}> .\n", prefix.0, prefix.1)
+    prefixes: BTreeSet<Prefix>,
+    declarations: Vec<fn(&mut RdfGraph)>,
 }
 
 impl RdfContext {
     pub fn add_prefix(&mut self, prefix: &Prefix) {
-        self.prefixes.insert(prefix.clone());
+        self.prefixes.insert(*prefix);
+    }
+
+    /// Prefixes sorted by name
+    pub fn prefixes(&self) -> impl Iterator<Item = &Prefix> {
+        self.prefixes.iter()
+    }
+
+    /// Statements that are generated once, before the items
+    pub fn add_declarations(&mut self, declare: fn(&mut RdfGraph)) {
+        if !self
+            .declarations
+            .iter()
+            .any(|x| *x as usize == declare as usize)
+        {
+            self.declarations.push(declare);
+        }
+    }
+}
+
+/// Triples generated by `ToRDF`, blank nodes are labeled in the order they are created
+#[derive(Default, Debug)]
+pub struct RdfGraph {
+    pub triples: Vec<Triple>,
+    blank_nodes: usize,
+}
+
+impl RdfGraph {
+    pub fn blank_node(&mut self) -> BlankNode {
+        self.blank_nodes += 1;
+        BlankNode::new_unchecked(format!("b{}", self.blank_nodes))
+    }
+
+    pub fn add(
+        &mut self,
+        subject: impl Into<Subject>,
+        predicate: impl Into<NamedNode>,
+        object: impl Into<Term>,
+    ) {
+        self.triples.push(Triple::new(subject, predicate, object));
     }
 
-    pub fn prefixes(&self) -> String {
-        self.prefixes.iter().map(prefix_to_string).collect()
+    /// Adds an rdf collection, and returns its head
+    pub fn add_list(&mut self, items: impl IntoIterator<Item = Term>) -> Term {
+        let mut items: Vec<Term> = items.into_iter().collect();
+        let mut list: Term = iri(&RDF, "nil").into();
+
+        while let Some(item) = items.pop() {
+            let node = self.blank_node();
+            self.add(node.clone(), iri(&RDF, "first"), item);
+            self.add(node.clone(), iri(&RDF, "rest"), list);
+            list = node.into();
+        }
+
+        list
     }
 }
 
+pub fn iri(prefix: &Prefix, local: &str) -> NamedNode {
+    NamedNode::new_unchecked(format!("{}{}", prefix.1, local))
+}
+
 pub trait ToRDF {
     fn add_ctx(ctx: &mut RdfContext);
-    fn to_rdf(self, target: &mut impl Write) -> std::io::Result<()>;
+    fn to_rdf(self, graph: &mut RdfGraph);
+}
+
+/// Items that are described by a new node, the caller links that node to its subject
+pub trait ToNode {
+    fn add_ctx(ctx: &mut RdfContext);
+    fn to_node(self, graph: &mut RdfGraph) -> BlankNode;
+}
+
+/// The triples describing the item, together with the context needed to serialize them
+pub fn to_graph<T: ToRDF>(item: T) -> (RdfContext, RdfGraph) {
+    let mut ctx = RdfContext::default();
+    T::add_ctx(&mut ctx);
+
+    let mut graph = RdfGraph::default();
+    for declare in &ctx.declarations {
+        declare(&mut graph);
+    }
+    item.to_rdf(&mut graph);
+
+    (ctx, graph)
+}
+
+/// Turtle document of the item, including the required prefixes
+pub fn to_turtle<T: ToRDF>(item: T) -> std::io::Result<Vec<u8>> {
+    let (ctx, graph) = to_graph(item);
+
+    let mut out = Vec::new();
+    write_turtle(&ctx, &graph.triples, &mut out)?;
+
+    Ok(out)
+}
+
+/// JSON-LD document of the item, with the prefixes as `@context`
+pub fn to_jsonld<T: ToRDF>(item: T) -> std::io::Result<Vec<u8>> {
+    let (ctx, graph) = to_graph(item);
+
+    let mut out = Vec::new();
+    write_jsonld(&ctx, &graph.triples, &mut out)?;
+
+    Ok(out)
+}
+
+/// Serializes the item in a format without prefixes, like N-Triples or RDF/XML
+pub fn to_format<T: ToRDF>(item: T, format: GraphFormat) -> std::io::Result<Vec<u8>> {
+    let (_, graph) = to_graph(item);
+
+    write_format(&graph.triples, format, Vec::new())
+}
+
+/// Writes the triples in a format without prefixes.
+///
+/// RDF/XML writes predicates as element names, predicates that don't end in a valid local name,
+/// like a percent-encoded property ending in digits, give an error instead of invalid XML.
+pub fn write_format<W: std::io::Write>(
+    triples: &[Triple],
+    format: GraphFormat,
+    out: W,
+) -> std::io::Result<W> {
+    if format == GraphFormat::RdfXml {
+        if let Some(triple) = triples
+            .iter()
+            .find(|x| !has_xml_local_name(x.predicate.as_str()))
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Predicate {} can't be written as RDF/XML, use another format",
+                    triple.predicate
+                ),
+            ));
+        }
+    }
+
+    let mut writer = GraphSerializer::from_format(format).triple_writer(out)?;
+    let mut seen = HashSet::new();
+    for triple in triples.iter().filter(|x| seen.insert(*x)) {
+        writer.write(triple)?;
+    }
+
+    writer.finish()
+}
+
+/// Whether the IRI ends in a name that starts with a letter or `_`, RDF/XML splits it there into
+/// a namespace and a local name
+fn has_xml_local_name(iri: &str) -> bool {
+    let local = iri
+        .rsplit(|c: char| c.is_ascii() && !(c.is_ascii_alphanumeric() || "_-.".contains(c)))
+        .next()
+        .unwrap_or_default();
+
+    local.contains(|c: char| !c.is_ascii() || c.is_ascii_alphabetic() || c == '_')
 }
 
 impl<T> ToRDF for &Vec<T>
@@ -53,12 +209,8 @@ where
         <&T>::add_ctx(ctx);
     }
 
-    fn to_rdf(self, target: &mut impl Write) -> std::io::Result<()> {
-        self.into_iter().try_for_each(|x| {
-            x.to_rdf(target)?;
-            write!(target, "\n")?;
-            Ok(())
-        })
+    fn to_rdf(self, graph: &mut RdfGraph) {
+        self.iter().for_each(|x| x.to_rdf(graph));
     }
 }
 
@@ -66,51 +218,81 @@ impl ToRDF for &DocumentedTypeDTO {
     fn add_ctx(ctx: &mut RdfContext) {
         ctx.add_prefix(&RDFS);
         ctx.add_prefix(&CONN);
+        <&BundleDTO>::add_ctx(ctx);
     }
 
-    fn to_rdf(self, buf: &mut impl Write) -> std::io::Result<()> {
-        write!(buf, "[] a :{};\n", self.ty)?;
+    fn to_rdf(self, graph: &mut RdfGraph) {
+        let subject = graph.blank_node();
+        graph.add(subject.clone(), iri(&RDF, "type"), iri(&CONN, &self.ty));
+
         if let Some(ref desc) = self.description {
-            write!(buf, "  rdfs:description {:?};\n", desc)?;
+            graph.add(
+                subject.clone(),
+                iri(&RDFS, "description"),
+                Literal::new_simple_literal(desc),
+            );
         }
 
-        self.tags
-            .iter()
-            .try_for_each(|x| write!(buf, "  :tag {:?};\n", x))?;
-
-        write!(buf, ".")?;
+        if let Some(ref bundle) = self.bundle {
+            let node = bundle.to_node(graph);
+            graph.add(subject.clone(), iri(&NIFI, "bundle"), node);
+        }
 
-        Ok(())
+        for tag in &self.tags {
+            graph.add(
+                subject.clone(),
+                iri(&CONN, "tag"),
+                Literal::new_simple_literal(tag),
+            );
+        }
     }
 }
 
 impl ToRDF for &ControllerServiceDTO {
     fn add_ctx(ctx: &mut RdfContext) {
+        ctx.add_prefix(&RDFS);
         ctx.add_prefix(&CONN);
         ctx.add_prefix(&SH);
         ctx.add_prefix(&NIFI);
         <&DescriptorDTO>::add_ctx(ctx);
+        <&BundleDTO>::add_ctx(ctx);
     }
 
-    fn to_rdf(self, buf: &mut impl Write) -> std::io::Result<()> {
-        write!(
-            buf,
-            r#"
-    nifi:{} a nifi:NifiProcess;
-        nifi:type {:?}.
+    fn to_rdf(self, graph: &mut RdfGraph) {
+        let ty = add_type(graph, &self.ty, self.bundle.as_ref());
 
-    [] a sh:NodeShape;
-        sh:targetClass nifi:{}; "#,
-            self.name, self.ty, self.name
-        )?;
+        if let Some(ref bundle) = self.bundle {
+            let node = bundle.to_node(graph);
+            graph.add(ty.clone(), iri(&NIFI, "bundle"), node);
+        }
 
-        self.descriptors.values().try_for_each(|x| x.to_rdf(buf))?;
+        graph.add(
+            ty.clone(),
+            iri(&NIFI, "type"),
+            Literal::new_simple_literal(&self.ty),
+        );
+
+        // Properties that reference a service expect an instance of the api, see `sh:class`
+        for api in self.apis.iter().flatten() {
+            graph.add(
+                NamedNode::new_unchecked(type_iri(&self.ty)),
+                iri(&RDFS, "subClassOf"),
+                NamedNode::new_unchecked(type_iri(&api.ty)),
+            );
+        }
+        add_legacy_paths(graph, self.descriptors.values().map(|x| x.name.as_str()));
 
-        write!(buf, ".",)?;
+        let shape = graph.blank_node();
+        graph.add(shape.clone(), iri(&RDF, "type"), iri(&SH, "NodeShape"));
+        graph.add(shape.clone(), iri(&SH, "targetClass"), ty);
 
-        Ok(())
+        for desc in self.descriptors.values() {
+            let node = desc.to_node(graph);
+            graph.add(shape.clone(), iri(&SH, "property"), node);
+        }
     }
 }
+
 impl<T, S> ToRDF for &VersionedEntity<T, S>
 where
     for<'a> &'a T: ToRDF,
@@ -118,11 +300,81 @@ where
     fn add_ctx(ctx: &mut RdfContext) {
         <&T>::add_ctx(ctx);
     }
-    fn to_rdf(self, target: &mut impl Write) -> std::io::Result<()> {
-        self.component.comp.to_rdf(target)
+    fn to_rdf(self, graph: &mut RdfGraph) {
+        self.component.comp.to_rdf(graph)
     }
 }
 
+/// Declares the class of a type in a bundle, and returns it
+fn add_type(graph: &mut RdfGraph, ty: &str, bundle: Option<&BundleDTO>) -> NamedNode {
+    let class = NamedNode::new_unchecked(bundle_type_iri(ty, bundle));
+    graph.add(class.clone(), iri(&RDF, "type"), iri(&NIFI, "NifiProcess"));
+
+    if bundle.is_some() {
+        graph.add(
+            class.clone(),
+            iri(&RDFS, "subClassOf"),
+            NamedNode::new_unchecked(type_iri(ty)),
+        );
+    }
+
+    // Inputs written for older ontologies type their processors with the simple name
+    let legacy = iri(&NIFI, type_name(ty));
+    if legacy.as_str() != type_iri(ty) {
+        graph.add(
+            legacy,
+            iri(&RDFS, "subClassOf"),
+            NamedNode::new_unchecked(type_iri(ty)),
+        );
+    }
+
+    class
+}
+
+/// Older ontologies named properties and relationships with a lowercase slug of their key, these
+/// slugs stay usable as a subproperty of the key. A slug shared by several keys of the type is
+/// left out, its values can't be told apart
+fn add_legacy_paths<'a>(graph: &mut RdfGraph, keys: impl IntoIterator<Item = &'a str>) {
+    let mut slugs: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for key in keys {
+        slugs.entry(legacy_slug(key)).or_default().push(key);
+    }
+
+    for (slug, keys) in slugs {
+        let path = match keys[..] {
+            [key] => make_path_safe(key),
+            _ => continue,
+        };
+
+        if slug != path {
+            graph.add(
+                iri(&NIFI, &slug),
+                iri(&RDFS, "subPropertyOf"),
+                iri(&NIFI, &path),
+            );
+        }
+    }
+}
+
+/// Links the generated path of a property or relationship to its nifi key
+fn add_mapping(graph: &mut RdfGraph, ty: &NamedNode, key: &str) {
+    let mapping = graph.blank_node();
+    let parameter = graph.blank_node();
+
+    graph.add(ty.clone(), iri(&NIFI, "mapping"), mapping.clone());
+    graph.add(mapping, iri(&FNO, "parameterMapping"), parameter.clone());
+    graph.add(
+        parameter.clone(),
+        iri(&FNOM, "functionParameter"),
+        iri(&NIFI, &make_path_safe(key)),
+    );
+    graph.add(
+        parameter,
+        iri(&FNOM, "implementationParameterPosition"),
+        Literal::new_simple_literal(key),
+    );
+}
+
 impl ToRDF for &ProcessorDTO {
     fn add_ctx(ctx: &mut RdfContext) {
         ctx.add_prefix(&NIFI);
@@ -133,120 +385,350 @@ impl ToRDF for &ProcessorDTO {
         ctx.add_prefix(&FNOM);
         <&RelationshipDTO>::add_ctx(ctx);
         <&DescriptorDTO>::add_ctx(ctx);
+        <&BundleDTO>::add_ctx(ctx);
     }
-    fn to_rdf(self, buf: &mut impl Write) -> std::io::Result<()> {
-        let desc_mapping: String = self
-            .config
-            .descriptors
-            .iter()
-            .map(|(_, x)| {
-                let path = make_path_safe(&x.name);
-                format!(
-                    "nifi:mapping [ fno:parameterMapping [ fnom:functionParameter nifi:{}; fnom:implementationParameterPosition {:?} ] ]; \n",
-                    path, x.name
-                )
-            }).collect();
 
-        let rel_mapping: String =  self
-            .relationships
-            .iter()
-            .map(|x| {
-                let path = make_path_safe(&x.name);
-                format!(
-                    "nifi:mapping [ fno:parameterMapping [ fnom:functionParameter nifi:{}; fnom:implementationParameterPosition {:?} ] ]; \n",
-                    path, x.name
-                )
-            }).collect();
-
-        write!(
-            buf,
-            r#"
-    nifi:{} a nifi:NifiProcess;
-        {}{}
-        nifi:type {:?}.
-
-    [] a sh:NodeShape;
-       sh:targetClass nifi:{};
-       sh:property [
-          sh:class :ReaderChannel;
-          sh:path nifi:INCOMING_CHANNEL;
-          sh:name "Incoming channel";
-          sh:description "Combination of all incoming channels";
-        ];
-        "#,
-            self.name, desc_mapping, rel_mapping, self.ty, self.name
-        )?;
-
-        self.relationships.iter().try_for_each(|x| x.to_rdf(buf))?;
-        self.config
-            .descriptors
-            .values()
-            .try_for_each(|x| x.to_rdf(buf))?;
-
-        write!(buf, ".")?;
-        Ok(())
-    }
-}
-impl ToRDF for &RelationshipDTO {
+    fn to_rdf(self, graph: &mut RdfGraph) {
+        let ty = add_type(graph, &self.ty, self.bundle.as_ref());
+
+        let mut relationships: Vec<_> = self.relationships.iter().collect();
+        relationships.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for desc in self.config.descriptors.values() {
+            add_mapping(graph, &ty, &desc.name);
+        }
+        for rel in &relationships {
+            add_mapping(graph, &ty, &rel.name);
+        }
+        add_legacy_paths(
+            graph,
+            self.config
+                .descriptors
+                .values()
+                .map(|x| x.name.as_str())
+                .chain(relationships.iter().map(|x| x.name.as_str())),
+        );
+
+        if let Some(ref bundle) = self.bundle {
+            let node = bundle.to_node(graph);
+            graph.add(ty.clone(), iri(&NIFI, "bundle"), node);
+        }
+
+        graph.add(
+            ty.clone(),
+            iri(&NIFI, "type"),
+            Literal::new_simple_literal(&self.ty),
+        );
+
+        let shape = graph.blank_node();
+        graph.add(shape.clone(), iri(&RDF, "type"), iri(&SH, "NodeShape"));
+        graph.add(shape.clone(), iri(&SH, "targetClass"), ty);
+
+        let incoming = graph.blank_node();
+        graph.add(shape.clone(), iri(&SH, "property"), incoming.clone());
+        graph.add(
+            incoming.clone(),
+            iri(&SH, "class"),
+            iri(&CONN, "ReaderChannel"),
+        );
+        graph.add(
+            incoming.clone(),
+            iri(&SH, "path"),
+            iri(&NIFI, "INCOMING_CHANNEL"),
+        );
+        graph.add(
+            incoming.clone(),
+            iri(&SH, "name"),
+            Literal::new_simple_literal("Incoming channel"),
+        );
+        graph.add(
+            incoming,
+            iri(&SH, "description"),
+            Literal::new_simple_literal("Combination of all incoming channels"),
+        );
+
+        for rel in relationships {
+            let node = rel.to_node(graph);
+            graph.add(shape.clone(), iri(&SH, "property"), node);
+        }
+
+        for desc in self.config.descriptors.values() {
+            let node = desc.to_node(graph);
+            graph.add(shape.clone(), iri(&SH, "property"), node);
+        }
+    }
+}
+
+impl ToNode for &BundleDTO {
+    fn add_ctx(ctx: &mut RdfContext) {
+        ctx.add_prefix(&NIFI);
+        // Classes of a type in a bundle are a subclass of the type
+        ctx.add_prefix(&RDFS);
+    }
+
+    fn to_node(self, graph: &mut RdfGraph) -> BlankNode {
+        let node = graph.blank_node();
+        graph.add(
+            node.clone(),
+            iri(&NIFI, "bundleGroup"),
+            Literal::new_simple_literal(&self.group),
+        );
+        graph.add(
+            node.clone(),
+            iri(&NIFI, "bundleArtifact"),
+            Literal::new_simple_literal(&self.artifact),
+        );
+        graph.add(
+            node.clone(),
+            iri(&NIFI, "bundleVersion"),
+            Literal::new_simple_literal(&self.version),
+        );
+        node
+    }
+}
+
+impl ToNode for &RelationshipDTO {
     fn add_ctx(ctx: &mut RdfContext) {
         ctx.add_prefix(&SH);
         ctx.add_prefix(&NIFI);
     }
-    fn to_rdf(self, buf: &mut impl Write) -> std::io::Result<()> {
-        let path = make_path_safe(&self.name);
 
-        write!(
-            buf,
-            r#" sh:property [
-          sh:class :WriterChannel;
-          sh:path nifi:{};
-          sh:name {:?};   "#,
-            path, self.name
-        )?;
+    fn to_node(self, graph: &mut RdfGraph) -> BlankNode {
+        let node = graph.blank_node();
+
+        graph.add(node.clone(), iri(&SH, "class"), iri(&CONN, "WriterChannel"));
+        graph.add(
+            node.clone(),
+            iri(&SH, "path"),
+            iri(&NIFI, &make_path_safe(&self.name)),
+        );
+        graph.add(
+            node.clone(),
+            iri(&SH, "name"),
+            Literal::new_simple_literal(&self.name),
+        );
 
         if let Some(ref desc) = self.description {
-            write!(buf, "  sh:description {:?};", desc)?;
+            graph.add(
+                node.clone(),
+                iri(&SH, "description"),
+                Literal::new_simple_literal(desc),
+            );
         }
-        write!(buf, "];")?;
 
-        Ok(())
+        node
     }
 }
-impl ToRDF for &DescriptorDTO {
+
+impl ToNode for &DescriptorDTO {
     fn add_ctx(ctx: &mut RdfContext) {
         ctx.add_prefix(&SH);
         ctx.add_prefix(&XSD);
+        ctx.add_prefix(&NIFI);
+        ctx.add_prefix(&RDF);
+        ctx.add_prefix(&RDFS);
+        ctx.add_declarations(datatype::declare);
     }
-    fn to_rdf(self, buf: &mut impl Write) -> std::io::Result<()> {
-        let path = make_path_safe(&self.name);
-        write!(
-            buf,
-            r#"
-    sh:property [
-          sh:datatype xsd:string;
-          sh:path nifi:{};
-          sh:name {:?};
-          sh:description {:?};
-          sh:minCount {};"#,
-            path, self.display, self.description, self.required as u32
-        )?;
+
+    fn to_node(self, graph: &mut RdfGraph) -> BlankNode {
+        let node = graph.blank_node();
+
+        // Properties that reference a controller service expect an instance of the service api
+        match self.identifies_controller_service {
+            Some(ref service) => graph.add(
+                node.clone(),
+                iri(&SH, "class"),
+                NamedNode::new_unchecked(type_iri(service)),
+            ),
+            None => graph.add(
+                node.clone(),
+                iri(&SH, "datatype"),
+                NamedNode::new_unchecked(Datatype::infer(self).iri()),
+            ),
+        }
+
+        graph.add(
+            node.clone(),
+            iri(&SH, "path"),
+            iri(&NIFI, &make_path_safe(&self.name)),
+        );
+        graph.add(
+            node.clone(),
+            iri(&SH, "name"),
+            Literal::new_simple_literal(&self.display),
+        );
+        graph.add(
+            node.clone(),
+            iri(&SH, "description"),
+            Literal::new_simple_literal(&self.description),
+        );
+        graph.add(
+            node.clone(),
+            iri(&SH, "minCount"),
+            Literal::from(i64::from(self.required)),
+        );
 
         if let Some(ref df) = self.default_value {
-            write!(buf, "sh:defaultValue {:?};", df)?;
+            graph.add(
+                node.clone(),
+                iri(&SH, "defaultValue"),
+                Literal::new_simple_literal(df),
+            );
+        }
+
+        if let Some(ref values) = self.allowable_values {
+            let values: Vec<_> = values.iter().map(|x| &x.allowable_value).collect();
+
+            let list = graph.add_list(
+                values
+                    .iter()
+                    .map(|x| Literal::new_simple_literal(&x.value).into()),
+            );
+            graph.add(node.clone(), iri(&SH, "in"), list);
+
+            for value in values {
+                let allowed = graph.blank_node();
+                graph.add(node.clone(), iri(&NIFI, "allowableValue"), allowed.clone());
+                graph.add(
+                    allowed.clone(),
+                    iri(&RDF, "value"),
+                    Literal::new_simple_literal(&value.value),
+                );
+                if let Some(ref display) = value.display {
+                    graph.add(
+                        allowed.clone(),
+                        iri(&RDFS, "label"),
+                        Literal::new_simple_literal(display),
+                    );
+                }
+                if let Some(ref desc) = value.description {
+                    graph.add(
+                        allowed,
+                        iri(&RDFS, "comment"),
+                        Literal::new_simple_literal(desc),
+                    );
+                }
+            }
         }
 
-        write!(buf, "] ;")?;
-        Ok(())
+        if self.sensitive {
+            graph.add(node.clone(), iri(&NIFI, "sensitive"), Literal::from(true));
+        }
+
+        if let Some(ref scope) = self.expression_language_scope {
+            graph.add(
+                node.clone(),
+                iri(&NIFI, "expressionLanguageScope"),
+                Literal::new_simple_literal(scope),
+            );
+        }
+
+        for dependency in self.dependencies.iter().flatten() {
+            let depends = graph.blank_node();
+            graph.add(node.clone(), iri(&NIFI, "dependsOn"), depends.clone());
+            graph.add(
+                depends.clone(),
+                iri(&NIFI, "property"),
+                iri(&NIFI, &make_path_safe(&dependency.property_name)),
+            );
+            for value in dependency.dependent_values.iter().flatten() {
+                graph.add(
+                    depends.clone(),
+                    iri(&NIFI, "dependentValue"),
+                    Literal::new_simple_literal(value),
+                );
+            }
+        }
+
+        node
     }
 }
 
-fn make_path_safe(path: &str) -> String {
-    path.chars()
+/// Simple name of a java type, this is also the name nifi gives new processors
+pub(crate) fn type_name(ty: &str) -> &str {
+    ty.rsplit('.').next().unwrap_or(ty)
+}
+
+/// IRI of the class generated for a java type, processors in the input are typed with it.
+///
+/// The fully qualified name is used, so types with the same simple name in different bundles
+/// don't share a class.
+pub(crate) fn type_iri(ty: &str) -> String {
+    format!("{}{}", NIFI.1, make_path_safe(ty))
+}
+
+/// IRI of the class of a java type in one bundle, a subclass of `type_iri`.
+///
+/// It holds the shape, mappings and `nifi:bundle` of that bundle version, so versions with
+/// different properties don't share a shape. Group, artifact and version never contain a `/`,
+/// so the type, group, artifact and version are joined with one.
+pub(crate) fn bundle_type_iri(ty: &str, bundle: Option<&BundleDTO>) -> String {
+    match bundle {
+        Some(b) => {
+            let name = format!("{}/{}/{}/{}", ty, b.group, b.artifact, b.version);
+            format!("{}{}", NIFI.1, make_path_safe(&name))
+        }
+        None => type_iri(ty),
+    }
+}
+
+/// Local name older ontologies gave a property or relationship, letters are lowercased and every
+/// other character becomes a `-`
+pub(crate) fn legacy_slug(key: &str) -> String {
+    key.chars()
         .map(|x| {
-            x.is_alphabetic()
-                .then_some(x)
-                .map(|y| y.to_ascii_lowercase())
-                .unwrap_or('-')
+            if x.is_alphabetic() {
+                x.to_ascii_lowercase()
+            } else {
+                '-'
+            }
         })
         .collect()
 }
+
+/// Local name for a nifi property, relationship or type.
+///
+/// ASCII letters, digits, `_`, `-` and `.` are kept (when they are valid in a prefixed name),
+/// every other byte is percent-encoded. Different keys never get the same local name and the
+/// original key is recovered by percent-decoding.
+pub(crate) fn make_path_safe(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut out = String::with_capacity(path.len());
+
+    for (i, b) in bytes.iter().enumerate() {
+        let keep = match b {
+            b'-' => i > 0,
+            b'.' => i > 0 && i + 1 < bytes.len(),
+            _ => b.is_ascii_alphanumeric() || *b == b'_',
+        };
+
+        if keep {
+            out.push(*b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+
+    out
+}
+
+/// The nifi key of a local name made by `make_path_safe`, `None` when it is not percent-encoded
+/// UTF-8
+pub(crate) fn path_key(local: &str) -> Option<String> {
+    let bytes = local.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = local.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(out).ok()
+}
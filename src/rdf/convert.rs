@@ -0,0 +1,76 @@
+use std::io::{self, BufRead, Write};
+
+use oxigraph::io::{DatasetFormat, DatasetParser, GraphFormat, GraphParser};
+use oxigraph::model::Triple;
+
+use super::{read_jsonld, write_format, write_jsonld, write_turtle, RdfContext};
+
+/// Serialization of the flow files on a channel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Serialization {
+    Turtle,
+    NQuads,
+    JsonLd,
+}
+
+impl Serialization {
+    /// Serialization of a content type, parameters like `charset` are ignored
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        let mime = mime.split(';').next().unwrap_or_default().trim();
+        match mime.to_lowercase().as_str() {
+            "text/turtle" => Some(Serialization::Turtle),
+            "application/n-quads" | "application/n-triples" => Some(Serialization::NQuads),
+            "application/ld+json" => Some(Serialization::JsonLd),
+            _ => None,
+        }
+    }
+
+    pub fn mime(self) -> &'static str {
+        match self {
+            Serialization::Turtle => "text/turtle",
+            Serialization::NQuads => "application/n-quads",
+            Serialization::JsonLd => "application/ld+json",
+        }
+    }
+}
+
+/// Parses the document and writes its triples in another serialization, a document that does not
+/// parse gives an error. Named graphs of N-Quads documents end up in the default graph
+pub fn convert(
+    input: impl BufRead,
+    from: Serialization,
+    to: Serialization,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let triples = parse(input, from)?;
+
+    match to {
+        Serialization::Turtle => write_turtle(&RdfContext::default(), &triples, out),
+        // N-Triples documents are N-Quads documents without graph names
+        Serialization::NQuads => write_format(&triples, GraphFormat::NTriples, out).map(|_| ()),
+        Serialization::JsonLd => write_jsonld(&RdfContext::default(), &triples, out),
+    }
+}
+
+fn parse(input: impl BufRead, from: Serialization) -> io::Result<Vec<Triple>> {
+    match from {
+        Serialization::Turtle => GraphParser::from_format(GraphFormat::Turtle)
+            .read_triples(input)
+            .map_err(invalid)?
+            .map(|x| x.map_err(invalid))
+            .collect(),
+        Serialization::NQuads => DatasetParser::from_format(DatasetFormat::NQuads)
+            .read_quads(input)
+            .map_err(invalid)?
+            .map(|x| {
+                x.map(|q| Triple::new(q.subject, q.predicate, q.object))
+                    .map_err(invalid)
+            })
+            .collect(),
+        Serialization::JsonLd => read_jsonld(input, None),
+    }
+}
+
+fn invalid(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
@@ -0,0 +1,601 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read, Write};
+
+use oxigraph::model::vocab::{rdf, xsd};
+use oxigraph::model::{BlankNode, Literal, NamedNode, Subject, Term, Triple};
+use oxiri::Iri;
+use serde_json::{json, Map, Value};
+
+use super::prefix::Prefix;
+use super::RdfContext;
+
+/// Writes the triples as a flattened JSON-LD document.
+///
+/// The `@context` holds the prefixes of the context, the empty prefix becomes the `@vocab`.
+pub fn write_jsonld(ctx: &RdfContext, triples: &[Triple], out: &mut impl Write) -> io::Result<()> {
+    let prefixes: Vec<Prefix> = ctx.prefixes().copied().collect();
+
+    let mut context = Map::new();
+    for (prefix, namespace) in &prefixes {
+        let key = if prefix.is_empty() { "@vocab" } else { prefix };
+        context.insert(key.to_string(), Value::from(*namespace));
+    }
+
+    let writer = JsonLdWriter { prefixes };
+
+    let mut seen = HashSet::new();
+    let mut order: Vec<&Subject> = Vec::new();
+    let mut nodes: HashMap<&Subject, Map<String, Value>> = HashMap::new();
+
+    for triple in triples.iter().filter(|x| seen.insert(*x)) {
+        let node = nodes.entry(&triple.subject).or_insert_with(|| {
+            order.push(&triple.subject);
+            let mut node = Map::new();
+            node.insert("@id".into(), writer.subject(&triple.subject).into());
+            node
+        });
+
+        let (key, value) = if triple.predicate.as_str() == rdf::TYPE.as_str() {
+            let value = match triple.object {
+                Term::NamedNode(ref ty) => writer.vocab(ty.as_str()).into(),
+                ref other => writer.object(other),
+            };
+            (String::from("@type"), value)
+        } else {
+            (
+                writer.vocab(triple.predicate.as_str()),
+                writer.object(&triple.object),
+            )
+        };
+
+        match node.entry(key).or_insert_with(|| Value::Array(Vec::new())) {
+            Value::Array(values) => values.push(value),
+            _ => unreachable!("all properties are arrays"),
+        }
+    }
+
+    let graph: Vec<Value> = order
+        .into_iter()
+        .filter_map(|x| nodes.remove(x))
+        .map(Value::Object)
+        .collect();
+
+    let document = json!({
+        "@context": context,
+        "@graph": graph,
+    });
+
+    serde_json::to_writer_pretty(&mut *out, &document)?;
+    writeln!(out)
+}
+
+struct JsonLdWriter {
+    prefixes: Vec<Prefix>,
+}
+
+impl JsonLdWriter {
+    /// Compact IRI, prefixes only apply to `@id` values when they have a name
+    fn compact(&self, iri: &str) -> String {
+        self.prefixes
+            .iter()
+            .filter(|(prefix, namespace)| !prefix.is_empty() && iri.starts_with(namespace))
+            .max_by_key(|(_, namespace)| namespace.len())
+            .map(|(prefix, namespace)| format!("{}:{}", prefix, &iri[namespace.len()..]))
+            .unwrap_or_else(|| iri.to_string())
+    }
+
+    /// Property names and types are also resolved against the `@vocab`
+    fn vocab(&self, iri: &str) -> String {
+        let vocab = self
+            .prefixes
+            .iter()
+            .find(|(prefix, _)| prefix.is_empty())
+            .and_then(|(_, namespace)| iri.strip_prefix(namespace))
+            .filter(|local| !local.is_empty() && !local.contains(':'));
+
+        match vocab {
+            Some(local) => local.to_string(),
+            None => self.compact(iri),
+        }
+    }
+
+    fn subject(&self, subject: &Subject) -> String {
+        match subject {
+            Subject::NamedNode(node) => self.compact(node.as_str()),
+            Subject::BlankNode(node) => format!("_:{}", node.as_str()),
+            other => other.to_string(),
+        }
+    }
+
+    fn object(&self, object: &Term) -> Value {
+        match object {
+            Term::NamedNode(node) => json!({ "@id": self.compact(node.as_str()) }),
+            Term::BlankNode(node) => json!({ "@id": format!("_:{}", node.as_str()) }),
+            Term::Literal(literal) => self.literal(literal),
+            other => json!({ "@id": other.to_string() }),
+        }
+    }
+
+    fn literal(&self, literal: &Literal) -> Value {
+        if let Some(language) = literal.language() {
+            return json!({ "@value": literal.value(), "@language": language });
+        }
+
+        if literal.datatype() == xsd::STRING {
+            return Value::from(literal.value());
+        }
+
+        json!({
+            "@value": literal.value(),
+            "@type": self.compact(literal.datatype().as_str()),
+        })
+    }
+}
+
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const XSD_NS: &str = "http://www.w3.org/2001/XMLSchema#";
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// How a term of the `@context` is expanded
+#[derive(Clone, Debug)]
+struct TermDefinition {
+    id: String,
+    /// `@id`, `@vocab` or a datatype IRI
+    ty: Option<String>,
+    list: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Context {
+    base: Option<Iri<String>>,
+    vocab: Option<String>,
+    language: Option<String>,
+    terms: HashMap<String, TermDefinition>,
+}
+
+impl Context {
+    /// Applies a local context, remote contexts are not supported
+    fn merge(&self, local: &Value) -> io::Result<Context> {
+        let mut out = self.clone();
+
+        let locals = match local {
+            Value::Array(xs) => xs.iter().collect(),
+            x => vec![x],
+        };
+
+        for local in locals {
+            let local = match local {
+                Value::Null => {
+                    out = Context {
+                        base: self.base.clone(),
+                        ..Context::default()
+                    };
+                    continue;
+                }
+                Value::Object(x) => x,
+                Value::String(x) => {
+                    return Err(invalid(format!(
+                        "Remote context {} is not supported, embed the context in the document",
+                        x
+                    )))
+                }
+                _ => return Err(invalid("Invalid @context")),
+            };
+
+            if let Some(base) = local.get("@base") {
+                out.base = match base {
+                    Value::String(x) => Some(out.resolve(x)?),
+                    _ => None,
+                };
+            }
+
+            if let Some(vocab) = local.get("@vocab") {
+                out.vocab = match vocab {
+                    Value::String(x) => Some(out.expand(x, true)?),
+                    _ => None,
+                };
+            }
+
+            if let Some(language) = local.get("@language") {
+                out.language = language.as_str().map(|x| x.to_lowercase());
+            }
+
+            let mut defining = HashSet::new();
+            for term in local.keys().filter(|x| !x.starts_with('@')) {
+                out.define(term, local, &mut defining)?;
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Defines a term, terms of the same local context that it depends on are defined first
+    fn define(
+        &mut self,
+        term: &str,
+        local: &Map<String, Value>,
+        defining: &mut HashSet<String>,
+    ) -> io::Result<()> {
+        if !defining.insert(term.to_string()) {
+            return Ok(());
+        }
+
+        let value = &local[term];
+        let (id, ty, container) = match value {
+            Value::Null => {
+                self.terms.remove(term);
+                return Ok(());
+            }
+            Value::String(x) => (Some(x.as_str()), None, None),
+            Value::Object(x) => (
+                x.get("@id").and_then(Value::as_str),
+                x.get("@type").and_then(Value::as_str),
+                x.get("@container").and_then(Value::as_str),
+            ),
+            _ => return Err(invalid(format!("Invalid definition of term {}", term))),
+        };
+
+        let id = id.unwrap_or(term);
+        if let Some((prefix, _)) = id.split_once(':') {
+            if local.contains_key(prefix) && prefix != term {
+                self.define(prefix, local, defining)?;
+            }
+        }
+
+        let id = if id == term && !term.contains(':') {
+            match self.vocab {
+                Some(ref vocab) => format!("{}{}", vocab, term),
+                None => return Err(invalid(format!("Term {} does not expand to an IRI", term))),
+            }
+        } else {
+            self.expand(id, true)?
+        };
+
+        let ty = match ty {
+            Some(x @ ("@id" | "@vocab")) => Some(x.to_string()),
+            Some(x) if x.starts_with('@') => {
+                return Err(invalid(format!(
+                    "Type {} of term {} is not supported",
+                    x, term
+                )))
+            }
+            Some(x) => Some(self.expand(x, true)?),
+            None => None,
+        };
+
+        // Language, index and id maps would be read as node objects
+        if let Some(x) = container.filter(|x| !matches!(*x, "@list" | "@set")) {
+            return Err(invalid(format!(
+                "Container {} of term {} is not supported",
+                x, term
+            )));
+        }
+
+        self.terms.insert(
+            term.to_string(),
+            TermDefinition {
+                id,
+                ty,
+                list: container == Some("@list"),
+            },
+        );
+
+        Ok(())
+    }
+
+    fn resolve(&self, iri: &str) -> io::Result<Iri<String>> {
+        match self.base {
+            Some(ref base) => base.resolve(iri),
+            None => Iri::parse(iri.to_string()),
+        }
+        .map_err(|e| invalid(format!("Invalid IRI {}: {}", iri, e)))
+    }
+
+    /// Expands a compact IRI, term or relative IRI
+    fn expand(&self, value: &str, vocab: bool) -> io::Result<String> {
+        if value.starts_with('@') {
+            return Ok(value.to_string());
+        }
+
+        if vocab {
+            if let Some(def) = self.terms.get(value) {
+                return Ok(def.id.clone());
+            }
+        }
+
+        if let Some((prefix, suffix)) = value.split_once(':') {
+            if prefix == "_" || suffix.starts_with("//") {
+                return Ok(value.to_string());
+            }
+            if let Some(def) = self.terms.get(prefix) {
+                return Ok(format!("{}{}", def.id, suffix));
+            }
+            return Ok(value.to_string());
+        }
+
+        // Keys without a mapping are dropped, so they are not resolved against the base
+        if vocab {
+            return Ok(match self.vocab {
+                Some(ref v) => format!("{}{}", v, value),
+                None => value.to_string(),
+            });
+        }
+
+        Ok(self.resolve(value)?.into_inner())
+    }
+}
+
+struct JsonLdReader {
+    triples: Vec<Triple>,
+    blank_nodes: HashMap<String, BlankNode>,
+}
+
+impl JsonLdReader {
+    fn node_id(&mut self, id: &str) -> Subject {
+        match id.strip_prefix("_:") {
+            Some(label) => self
+                .blank_nodes
+                .entry(label.to_string())
+                .or_default()
+                .clone()
+                .into(),
+            None => NamedNode::new_unchecked(id).into(),
+        }
+    }
+
+    fn iri(&mut self, iri: String) -> Option<Subject> {
+        if iri.starts_with("_:") {
+            Some(self.node_id(&iri))
+        } else if iri.contains(':') {
+            Some(NamedNode::new_unchecked(iri).into())
+        } else {
+            None
+        }
+    }
+
+    /// Adds the triples of a node object and returns its subject
+    fn node(&mut self, node: &Map<String, Value>, ctx: &Context) -> io::Result<Subject> {
+        let ctx = match node.get("@context") {
+            Some(local) => ctx.merge(local)?,
+            None => ctx.clone(),
+        };
+
+        let subject = match node.get("@id").and_then(Value::as_str) {
+            Some(id) => {
+                let id = ctx.expand(id, false)?;
+                self.iri(id)
+                    .ok_or_else(|| invalid("@id does not expand to an IRI"))?
+            }
+            None => BlankNode::default().into(),
+        };
+
+        for (key, value) in node {
+            match key.as_str() {
+                "@context" | "@id" | "@index" => {}
+                "@type" => {
+                    for ty in as_array(value) {
+                        let ty = ty
+                            .as_str()
+                            .ok_or_else(|| invalid("@type must be a string"))?;
+                        if let Some(Subject::NamedNode(ty)) = self.iri(ctx.expand(ty, true)?) {
+                            let predicate = NamedNode::new_unchecked(format!("{}type", RDF_NS));
+                            self.triples
+                                .push(Triple::new(subject.clone(), predicate, ty));
+                        }
+                    }
+                }
+                // Only a top level @graph without other properties is the default graph
+                "@graph" => {
+                    return Err(invalid(
+                        "Named graphs are not supported, use TriG or N-Quads for pipelines",
+                    ));
+                }
+                x if x.starts_with('@') => {
+                    return Err(invalid(format!("{} is not supported", x)));
+                }
+                x => {
+                    let predicate = match self.iri(ctx.expand(x, true)?) {
+                        Some(Subject::NamedNode(x)) => x,
+                        _ => continue,
+                    };
+                    let def = ctx.terms.get(x).cloned();
+
+                    let objects = match def {
+                        Some(ref def) if def.list => vec![self.list(value, &ctx, Some(def))?],
+                        _ => {
+                            let mut objects = Vec::new();
+                            for item in as_array(value) {
+                                objects.extend(self.value(item, &ctx, def.as_ref())?);
+                            }
+                            objects
+                        }
+                    };
+
+                    for object in objects {
+                        self.triples
+                            .push(Triple::new(subject.clone(), predicate.clone(), object));
+                    }
+                }
+            }
+        }
+
+        Ok(subject)
+    }
+
+    fn list(
+        &mut self,
+        value: &Value,
+        ctx: &Context,
+        def: Option<&TermDefinition>,
+    ) -> io::Result<Term> {
+        let mut items = Vec::new();
+        for item in as_array(value) {
+            items.extend(self.value(item, ctx, def)?);
+        }
+
+        let first = NamedNode::new_unchecked(format!("{}first", RDF_NS));
+        let rest = NamedNode::new_unchecked(format!("{}rest", RDF_NS));
+        let mut list: Term = NamedNode::new_unchecked(format!("{}nil", RDF_NS)).into();
+
+        while let Some(item) = items.pop() {
+            let node = BlankNode::default();
+            self.triples
+                .push(Triple::new(node.clone(), first.clone(), item));
+            self.triples
+                .push(Triple::new(node.clone(), rest.clone(), list));
+            list = node.into();
+        }
+
+        Ok(list)
+    }
+
+    /// The object described by a json value, if any
+    fn value(
+        &mut self,
+        value: &Value,
+        ctx: &Context,
+        def: Option<&TermDefinition>,
+    ) -> io::Result<Option<Term>> {
+        let ty = def.and_then(|x| x.ty.as_deref());
+
+        let term = match value {
+            Value::Null => return Ok(None),
+            Value::Array(_) => return Err(invalid("Nested arrays are not supported")),
+            Value::String(x) => match ty {
+                Some("@id") => self.iri(ctx.expand(x, false)?).map(Term::from),
+                Some("@vocab") => self.iri(ctx.expand(x, true)?).map(Term::from),
+                Some(dt) => {
+                    Some(Literal::new_typed_literal(x, NamedNode::new_unchecked(dt)).into())
+                }
+                None => Some(
+                    match ctx.language {
+                        Some(ref lang) => Literal::new_language_tagged_literal_unchecked(x, lang),
+                        None => Literal::new_simple_literal(x),
+                    }
+                    .into(),
+                ),
+            },
+            Value::Bool(x) => Some(Literal::from(*x).into()),
+            Value::Number(x) => Some(number(x, ty).into()),
+            Value::Object(obj) => {
+                if let Some(v) = obj.get("@value") {
+                    Some(self.value_object(v, obj, ctx)?.into())
+                } else if let Some(list) = obj.get("@list") {
+                    Some(self.list(list, ctx, def)?)
+                } else {
+                    Some(self.node(obj, ctx)?.into())
+                }
+            }
+        };
+
+        Ok(term)
+    }
+
+    fn value_object(
+        &mut self,
+        value: &Value,
+        obj: &Map<String, Value>,
+        ctx: &Context,
+    ) -> io::Result<Literal> {
+        let ty = match obj.get("@type").and_then(Value::as_str) {
+            Some(ty) => Some(ctx.expand(ty, true)?),
+            None => None,
+        };
+
+        let literal = match (value, ty) {
+            (Value::Number(x), ty) => number(x, ty.as_deref()),
+            (Value::Bool(x), None) => Literal::from(*x),
+            (Value::Bool(x), Some(ty)) => {
+                Literal::new_typed_literal(x.to_string(), NamedNode::new_unchecked(ty))
+            }
+            (Value::String(x), Some(ty)) => {
+                Literal::new_typed_literal(x, NamedNode::new_unchecked(ty))
+            }
+            (Value::String(x), None) => match obj.get("@language").and_then(Value::as_str) {
+                Some(lang) => {
+                    Literal::new_language_tagged_literal_unchecked(x, lang.to_lowercase())
+                }
+                None => Literal::new_simple_literal(x),
+            },
+            _ => return Err(invalid("Invalid @value")),
+        };
+
+        Ok(literal)
+    }
+}
+
+/// A `@graph` with nothing but a context holds the nodes of the default graph, with an `@id` or
+/// other properties it is a named graph
+fn is_default_graph(obj: &Map<String, Value>) -> bool {
+    obj.contains_key("@graph") && obj.keys().all(|x| x == "@graph" || x == "@context")
+}
+
+fn as_array(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(xs) => xs.iter().collect(),
+        x => vec![x],
+    }
+}
+
+/// Integers become xsd:integer, other numbers xsd:double, unless a datatype is given
+fn number(x: &serde_json::Number, ty: Option<&str>) -> Literal {
+    let integer = x.is_i64() || x.is_u64();
+
+    match ty {
+        Some(ty) if ty != "@id" && ty != "@vocab" => {
+            Literal::new_typed_literal(x.to_string(), NamedNode::new_unchecked(ty))
+        }
+        _ if integer => Literal::new_typed_literal(
+            x.to_string(),
+            NamedNode::new_unchecked(format!("{}integer", XSD_NS)),
+        ),
+        _ => Literal::new_typed_literal(
+            format!("{:E}", x.as_f64().unwrap_or_default()),
+            NamedNode::new_unchecked(format!("{}double", XSD_NS)),
+        ),
+    }
+}
+
+/// Reads a JSON-LD document without expanding it through a full JSON-LD processor.
+///
+/// Embedded contexts, compact IRIs, `@vocab`, `@base`, `@language`, type coercion, `@list` and a
+/// top level `@graph` are supported. Remote contexts, named graphs, other containers than `@list`
+/// and `@set`, and keywords like `@reverse` or `@nest` give an error instead of different triples.
+pub fn read_jsonld<R: Read>(reader: R, base: Option<Iri<String>>) -> io::Result<Vec<Triple>> {
+    let document: Value = serde_json::from_reader(reader)?;
+
+    let ctx = Context {
+        base,
+        ..Context::default()
+    };
+    let mut reader = JsonLdReader {
+        triples: Vec::new(),
+        blank_nodes: HashMap::new(),
+    };
+
+    for item in as_array(&document) {
+        match item {
+            Value::Object(obj) if is_default_graph(obj) => {
+                let ctx = match obj.get("@context") {
+                    Some(local) => ctx.merge(local)?,
+                    None => ctx.clone(),
+                };
+                for node in as_array(&obj["@graph"]) {
+                    match node {
+                        Value::Object(node) => reader.node(node, &ctx)?,
+                        _ => return Err(invalid("Items of @graph must be objects")),
+                    };
+                }
+            }
+            Value::Object(obj) => {
+                reader.node(obj, &ctx)?;
+            }
+            _ => return Err(invalid("Top level JSON-LD items must be objects")),
+        }
+    }
+
+    Ok(reader.triples)
+}
@@ -0,0 +1,610 @@
+use std::fs;
+use std::path::PathBuf;
+
+use oxigraph::io::{GraphFormat, GraphParser};
+use oxigraph::model::vocab::xsd;
+use oxigraph::model::{Graph, Literal, NamedNode, Triple};
+
+use serde::de::DeserializeOwned;
+
+use crate::models::{ControllerServiceEntity, ProcessorEntity, RelationshipDTO};
+
+use super::prefix::{CONN, NIFI, RDF, RDFS, XSD};
+use super::{
+    convert, iri, read_jsonld, to_format, to_graph, to_jsonld, to_turtle, write_format,
+    write_jsonld, write_turtle, RdfContext, RdfGraph, Serialization, ToRDF,
+};
+
+fn log_message() -> ProcessorEntity {
+    let fixture = include_str!("../../tests/fixtures/log_message.json");
+    serde_json::from_str(fixture).unwrap()
+}
+
+/// Triples that need the less common parts of the serializers
+fn edge_cases() -> (RdfContext, RdfGraph) {
+    let mut ctx = RdfContext::default();
+    for prefix in [&CONN, &NIFI, &RDF, &RDFS, &XSD] {
+        ctx.add_prefix(prefix);
+    }
+
+    let mut graph = RdfGraph::default();
+    let subject = iri(&NIFI, "edge");
+
+    let list = graph.add_list([
+        Literal::new_simple_literal("first").into(),
+        iri(&NIFI, "second").into(),
+        Literal::new_typed_literal("3", xsd::INTEGER).into(),
+    ]);
+    graph.add(subject.clone(), iri(&NIFI, "list"), list);
+    let empty = graph.add_list(std::iter::empty());
+    graph.add(subject.clone(), iri(&NIFI, "empty"), empty);
+
+    // A list in a blank node in a blank node
+    let outer = graph.blank_node();
+    let inner = graph.blank_node();
+    let items = graph.add_list([inner.clone().into()]);
+    graph.add(subject.clone(), iri(&NIFI, "nested"), outer.clone());
+    graph.add(outer.clone(), iri(&NIFI, "inner"), inner.clone());
+    graph.add(outer, iri(&NIFI, "items"), items);
+    graph.add(
+        inner,
+        iri(&RDFS, "label"),
+        Literal::new_simple_literal("inner"),
+    );
+
+    // Referenced twice, so it needs a label
+    let shared = graph.blank_node();
+    graph.add(subject.clone(), iri(&NIFI, "shared"), shared.clone());
+    graph.add(iri(&NIFI, "other"), iri(&NIFI, "shared"), shared.clone());
+    graph.add(
+        shared,
+        iri(&RDFS, "label"),
+        Literal::new_simple_literal("shared"),
+    );
+
+    // Blank nodes that only reference each other
+    let a = graph.blank_node();
+    let b = graph.blank_node();
+    graph.add(a.clone(), iri(&NIFI, "next"), b.clone());
+    graph.add(b, iri(&NIFI, "next"), a);
+
+    // Never referenced
+    let lonely = graph.blank_node();
+    graph.add(
+        lonely,
+        iri(&RDFS, "label"),
+        Literal::new_simple_literal("lonely"),
+    );
+
+    let literals = [
+        Literal::new_language_tagged_literal_unchecked("hello", "en"),
+        Literal::new_language_tagged_literal_unchecked("hallo", "nl-be"),
+        Literal::new_simple_literal(
+            "tab\tline\nreturn\r \"quoted\" back\\slash \u{1}\u{7}\u{8}\u{c}\u{7f}",
+        ),
+        Literal::new_typed_literal("007", xsd::INTEGER),
+        Literal::new_typed_literal("-5", xsd::INTEGER),
+        Literal::new_typed_literal("1.5", xsd::INTEGER),
+        Literal::new_typed_literal("true", xsd::BOOLEAN),
+        Literal::new_typed_literal("30 secs", iri(&NIFI, "TimePeriod")),
+    ];
+    for literal in literals {
+        graph.add(subject.clone(), iri(&NIFI, "literal"), literal);
+    }
+
+    // Local names that can't be written as a prefixed name
+    graph.add(
+        subject.clone(),
+        iri(&NIFI, "Remote%20URL"),
+        iri(&NIFI, "a.b/c."),
+    );
+    graph.add(subject, iri(&CONN, "-dash"), iri(&RDF, "type"));
+
+    (ctx, graph)
+}
+
+fn canonical(triples: &[Triple]) -> Graph {
+    let mut graph = Graph::new();
+    for triple in triples {
+        graph.insert(triple);
+    }
+    graph.canonicalize();
+    graph
+}
+
+fn parse(data: &[u8], format: GraphFormat) -> Graph {
+    let triples: Vec<Triple> = GraphParser::from_format(format)
+        .read_triples(data)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    canonical(&triples)
+}
+
+/// Parses the serialized triples and compares them with the original ones
+fn assert_round_trip(triples: &[Triple], data: &[u8], format: GraphFormat) {
+    let actual = parse(data, format);
+    assert_eq!(
+        actual,
+        canonical(triples),
+        "{:?} does not round trip:\n{}",
+        format,
+        String::from_utf8_lossy(data)
+    );
+}
+
+/// Compares the turtle of a recorded processor or service with its golden file,
+/// run with `UPDATE_GOLDEN=1` to write the golden files instead.
+fn check_golden<T>(name: &str)
+where
+    T: DeserializeOwned,
+    for<'a> &'a T: ToRDF,
+{
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+
+    let fixture = fs::read(root.join("fixtures").join(format!("{}.json", name))).unwrap();
+    let entity: T = serde_json::from_slice(&fixture).unwrap();
+
+    let actual = String::from_utf8(to_turtle(&entity).unwrap()).unwrap();
+    let golden = root.join("golden").join(format!("{}.ttl", name));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&golden, &actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&golden).unwrap();
+    assert_eq!(actual, expected, "{} differs from its golden file", name);
+}
+
+#[test]
+fn log_message_golden() {
+    check_golden::<ProcessorEntity>("log_message");
+}
+
+#[test]
+fn csv_reader_golden() {
+    check_golden::<ControllerServiceEntity>("csv_reader");
+}
+
+/// Maps and relationships in another order give byte-identical documents
+#[test]
+fn output_is_deterministic() {
+    let failure = || RelationshipDTO {
+        name: "failure".into(),
+        description: None,
+    };
+
+    let mut entity = log_message();
+    entity.component.comp.relationships.push(failure());
+
+    // The fixture lists its properties and descriptors unsorted, a json value sorts its keys
+    let fixture = include_str!("../../tests/fixtures/log_message.json");
+    let sorted: serde_json::Value = serde_json::from_str(fixture).unwrap();
+    let mut permuted: ProcessorEntity = serde_json::from_value(sorted).unwrap();
+    permuted.component.comp.relationships.insert(0, failure());
+
+    for _ in 0..4 {
+        assert_eq!(to_turtle(&entity).unwrap(), to_turtle(&permuted).unwrap());
+        assert_eq!(
+            to_format(&entity, GraphFormat::NTriples).unwrap(),
+            to_format(&permuted, GraphFormat::NTriples).unwrap()
+        );
+        assert_eq!(to_jsonld(&entity).unwrap(), to_jsonld(&permuted).unwrap());
+    }
+}
+
+#[test]
+fn turtle_round_trips() {
+    let (_, graph) = to_graph(&log_message());
+    let turtle = to_turtle(&log_message()).unwrap();
+    assert_round_trip(&graph.triples, &turtle, GraphFormat::Turtle);
+
+    let (ctx, graph) = edge_cases();
+    let mut turtle = Vec::new();
+    write_turtle(&ctx, &graph.triples, &mut turtle).unwrap();
+    assert_round_trip(&graph.triples, &turtle, GraphFormat::Turtle);
+}
+
+#[test]
+fn ntriples_round_trips() {
+    let (_, graph) = to_graph(&log_message());
+    let ntriples = to_format(&log_message(), GraphFormat::NTriples).unwrap();
+    assert_round_trip(&graph.triples, &ntriples, GraphFormat::NTriples);
+
+    let (_, graph) = edge_cases();
+    let ntriples = write_format(&graph.triples, GraphFormat::NTriples, Vec::new()).unwrap();
+    assert_round_trip(&graph.triples, &ntriples, GraphFormat::NTriples);
+}
+
+#[test]
+fn rdfxml_round_trips() {
+    let (_, graph) = to_graph(&log_message());
+    let rdfxml = to_format(&log_message(), GraphFormat::RdfXml).unwrap();
+    assert_round_trip(&graph.triples, &rdfxml, GraphFormat::RdfXml);
+
+    // Split into a namespace ending in `%20` and the local name `URL`
+    let mut graph = RdfGraph::default();
+    graph.add(
+        iri(&NIFI, "edge"),
+        iri(&NIFI, "Remote%20URL"),
+        Literal::new_simple_literal("http://localhost:8080"),
+    );
+    let rdfxml = write_format(&graph.triples, GraphFormat::RdfXml, Vec::new()).unwrap();
+    assert_round_trip(&graph.triples, &rdfxml, GraphFormat::RdfXml);
+}
+
+#[test]
+fn rdfxml_rejects_predicates_without_local_name() {
+    let mut graph = RdfGraph::default();
+    graph.add(
+        iri(&NIFI, "edge"),
+        iri(&NIFI, "Port%208080"),
+        Literal::new_simple_literal("8080"),
+    );
+
+    assert!(write_format(&graph.triples, GraphFormat::RdfXml, Vec::new()).is_err());
+    assert!(write_format(&graph.triples, GraphFormat::NTriples, Vec::new()).is_ok());
+}
+
+#[test]
+fn jsonld_round_trips() {
+    let (_, graph) = to_graph(&log_message());
+    let jsonld = to_jsonld(&log_message()).unwrap();
+    let actual = read_jsonld(jsonld.as_slice(), None).unwrap();
+    assert_eq!(canonical(&actual), canonical(&graph.triples));
+
+    let (ctx, graph) = edge_cases();
+    let mut jsonld = Vec::new();
+    write_jsonld(&ctx, &graph.triples, &mut jsonld).unwrap();
+    let actual = read_jsonld(jsonld.as_slice(), None).unwrap();
+    assert_eq!(
+        canonical(&actual),
+        canonical(&graph.triples),
+        "JSON-LD does not round trip:\n{}",
+        String::from_utf8_lossy(&jsonld)
+    );
+}
+
+#[test]
+fn property_iris_are_distinct() {
+    use super::make_path_safe;
+
+    assert_eq!(make_path_safe("Max Batch Size"), "Max%20Batch%20Size");
+    assert_eq!(make_path_safe("max-batch-size"), "max-batch-size");
+    assert_eq!(make_path_safe("SSL2"), "SSL2");
+    assert_eq!(make_path_safe("-a."), "%2Da%2E");
+    assert_eq!(make_path_safe("50%"), "50%25");
+}
+
+#[test]
+fn property_iris_map_back_to_their_key() {
+    use super::{make_path_safe, path_key};
+
+    for key in ["Max Batch Size", "a.b/c", "SSL2", "-a.", "50%", "é"] {
+        assert_eq!(path_key(&make_path_safe(key)).as_deref(), Some(key));
+    }
+
+    assert_eq!(path_key("50%2"), None);
+    assert_eq!(path_key("%ZZ"), None);
+    assert_eq!(path_key("%FF"), None);
+}
+
+#[test]
+fn bundles_get_their_own_class() {
+    use super::{bundle_type_iri, type_iri};
+    use crate::models::BundleDTO;
+
+    let bundle = |version: &str| BundleDTO {
+        group: "org.apache.nifi".into(),
+        artifact: "nifi-standard-nar".into(),
+        version: version.into(),
+    };
+    let ty = "org.apache.nifi.processors.standard.LogMessage";
+
+    let old = bundle_type_iri(ty, Some(&bundle("1.18.0")));
+    let new = bundle_type_iri(ty, Some(&bundle("1.19.0")));
+    assert_ne!(old, new);
+    assert_ne!(old, type_iri(ty));
+    assert_eq!(bundle_type_iri(ty, None), type_iri(ty));
+}
+
+#[test]
+fn services_are_subclasses_of_their_apis() {
+    use super::type_iri;
+    use crate::models::ControllerServiceDTO;
+
+    let ty = "org.apache.nifi.ssl.StandardRestrictedSSLContextService";
+    let apis = [
+        "org.apache.nifi.ssl.SSLContextService",
+        "org.apache.nifi.ssl.RestrictedSSLContextService",
+    ];
+    let service: ControllerServiceDTO = serde_json::from_value(serde_json::json!({
+        "name": "StandardRestrictedSSLContextService",
+        "type": ty,
+        "descriptors": {},
+        "controllerServiceApis": apis.map(|x| serde_json::json!({ "type": x })),
+    }))
+    .unwrap();
+
+    let (_, graph) = to_graph(&service);
+    let class = NamedNode::new_unchecked(type_iri(ty));
+    for api in apis {
+        let triple = Triple::new(
+            class.clone(),
+            iri(&RDFS, "subClassOf"),
+            NamedNode::new_unchecked(type_iri(api)),
+        );
+        assert!(graph.triples.contains(&triple), "{} is no {}", ty, api);
+    }
+}
+
+#[test]
+fn reads_jsonld() {
+    use oxigraph::model::Term;
+
+    let document = r#"{
+        "@context": {
+            "nifi": "https://w3id.org/conn/nifi#",
+            "url": { "@id": "nifi:URL" },
+            "next": { "@id": "nifi:success", "@type": "@id" }
+        },
+        "@id": "processor",
+        "@type": "nifi:org.apache.nifi.processors.standard.PostHTTP",
+        "url": "localhost:3000/posting",
+        "next": "_:writer"
+    }"#;
+
+    let base = oxiri::Iri::parse("http://example.com/ns#".to_string()).unwrap();
+    let triples = super::read_jsonld(document.as_bytes(), Some(base)).unwrap();
+
+    assert_eq!(triples.len(), 3);
+    assert!(triples
+        .iter()
+        .all(|x| x.subject.to_string() == "<http://example.com/processor>"));
+    assert!(triples.iter().any(|x| {
+        x.predicate == NamedNode::new_unchecked("https://w3id.org/conn/nifi#URL")
+            && x.object == Term::from(Literal::new_simple_literal("localhost:3000/posting"))
+    }));
+}
+
+/// Reads the JSON-LD document and compares it with the N-Triples
+fn assert_jsonld(document: &str, ntriples: &str) {
+    let triples = read_jsonld(document.as_bytes(), None).unwrap();
+    assert_eq!(
+        canonical(&triples),
+        parse(ntriples.as_bytes(), GraphFormat::NTriples)
+    );
+}
+
+#[test]
+fn jsonld_contexts() {
+    let document = r#"{
+        "@context": {
+            "@base": "http://example.com/base/",
+            "@vocab": "http://example.com/vocab#",
+            "@language": "en",
+            "ex": "http://example.com/ns#",
+            "name": "ex:name",
+            "knows": { "@id": "ex:knows", "@type": "@id" }
+        },
+        "@id": "alice",
+        "name": "Alice",
+        "age": { "@value": "5", "@type": "ex:years" },
+        "knows": "bob",
+        "ex:label": { "@value": "Alice", "@language": "NL" },
+        "ex:nested": {
+            "@context": { "ex": "http://other.example/#" },
+            "@id": "ex:x",
+            "ex:p": "q"
+        }
+    }"#;
+
+    let expected = r#"
+<http://example.com/base/alice> <http://example.com/ns#name> "Alice"@en .
+<http://example.com/base/alice> <http://example.com/vocab#age> "5"^^<http://example.com/ns#years> .
+<http://example.com/base/alice> <http://example.com/ns#knows> <http://example.com/base/bob> .
+<http://example.com/base/alice> <http://example.com/ns#label> "Alice"@nl .
+<http://example.com/base/alice> <http://example.com/ns#nested> <http://other.example/#x> .
+<http://other.example/#x> <http://other.example/#p> "q"@en .
+"#;
+    assert_jsonld(document, expected);
+}
+
+#[test]
+fn jsonld_graph_and_type_coercion() {
+    let document = r#"{
+        "@context": {
+            "@vocab": "http://example.com/vocab#",
+            "ex": "http://example.com/ns#",
+            "xsd": "http://www.w3.org/2001/XMLSchema#",
+            "next": { "@id": "ex:next", "@type": "@id" },
+            "kind": { "@id": "ex:kind", "@type": "@vocab" },
+            "size": { "@id": "ex:size", "@type": "xsd:integer" }
+        },
+        "@graph": [
+            {
+                "@id": "ex:a",
+                "@type": ["ex:Thing", "Other"],
+                "next": "_:b",
+                "kind": "Big",
+                "size": "3"
+            },
+            { "@id": "_:b", "ex:count": 2, "ex:ratio": 1.5, "ex:flag": true, "size": 4 }
+        ]
+    }"#;
+
+    let expected = r#"
+<http://example.com/ns#a> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://example.com/ns#Thing> .
+<http://example.com/ns#a> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://example.com/vocab#Other> .
+<http://example.com/ns#a> <http://example.com/ns#next> _:b .
+<http://example.com/ns#a> <http://example.com/ns#kind> <http://example.com/vocab#Big> .
+<http://example.com/ns#a> <http://example.com/ns#size> "3"^^<http://www.w3.org/2001/XMLSchema#integer> .
+_:b <http://example.com/ns#count> "2"^^<http://www.w3.org/2001/XMLSchema#integer> .
+_:b <http://example.com/ns#ratio> "1.5E0"^^<http://www.w3.org/2001/XMLSchema#double> .
+_:b <http://example.com/ns#flag> "true"^^<http://www.w3.org/2001/XMLSchema#boolean> .
+_:b <http://example.com/ns#size> "4"^^<http://www.w3.org/2001/XMLSchema#integer> .
+"#;
+    assert_jsonld(document, expected);
+}
+
+#[test]
+fn jsonld_lists() {
+    let document = r#"{
+        "@context": {
+            "ex": "http://example.com/ns#",
+            "items": { "@id": "ex:items", "@container": "@list" }
+        },
+        "@id": "ex:a",
+        "items": ["x", { "@id": "ex:y" }],
+        "ex:explicit": { "@list": [1] },
+        "ex:empty": { "@list": [] }
+    }"#;
+
+    let expected = r#"
+<http://example.com/ns#a> <http://example.com/ns#items> _:l1 .
+_:l1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> "x" .
+_:l1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> _:l2 .
+_:l2 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> <http://example.com/ns#y> .
+_:l2 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> <http://www.w3.org/1999/02/22-rdf-syntax-ns#nil> .
+<http://example.com/ns#a> <http://example.com/ns#explicit> _:l3 .
+_:l3 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> "1"^^<http://www.w3.org/2001/XMLSchema#integer> .
+_:l3 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> <http://www.w3.org/1999/02/22-rdf-syntax-ns#nil> .
+<http://example.com/ns#a> <http://example.com/ns#empty> <http://www.w3.org/1999/02/22-rdf-syntax-ns#nil> .
+"#;
+    assert_jsonld(document, expected);
+}
+
+#[test]
+fn jsonld_errors() {
+    let documents = [
+        ("{", "EOF"),
+        (r#"["x"]"#, "must be objects"),
+        (r#"{ "@context": "https://schema.org/" }"#, "Remote context"),
+        (r#"{ "@context": 5 }"#, "Invalid @context"),
+        (
+            r#"{ "@context": { "p": { "@type": "@id" } }, "p": "x" }"#,
+            "does not expand to an IRI",
+        ),
+        (
+            r#"{ "@context": { "p": { "@id": "http://e/#p", "@container": "@language" } } }"#,
+            "Container @language",
+        ),
+        (
+            r#"{ "@context": { "p": { "@id": "http://e/#p", "@type": "@json" } } }"#,
+            "Type @json",
+        ),
+        (r#"{ "@reverse": {} }"#, "@reverse is not supported"),
+        (r#"{ "http://e/#p": [[1]] }"#, "Nested arrays"),
+        (r#"{ "http://e/#p": { "@value": {} } }"#, "Invalid @value"),
+        (r#"{ "@type": 5 }"#, "@type must be a string"),
+        (
+            r#"{ "@context": [{ "ex": "http://e/#" }, "ctx.jsonld"] }"#,
+            "Remote context",
+        ),
+        (
+            r#"{ "@id": "http://e/#g", "@graph": [{ "@id": "http://e/#a" }] }"#,
+            "Named graphs",
+        ),
+        (
+            r#"{ "http://e/#p": { "@graph": [{ "@id": "http://e/#a" }] } }"#,
+            "Named graphs",
+        ),
+        (r#"{ "@graph": ["x"] }"#, "must be objects"),
+    ];
+
+    for (document, message) in documents {
+        let error = read_jsonld(document.as_bytes(), None).unwrap_err();
+        assert!(
+            error.to_string().contains(message),
+            "{} gave {}",
+            document,
+            error
+        );
+    }
+}
+
+#[test]
+fn converts_between_serializations() {
+    let (ctx, graph) = edge_cases();
+    let mut turtle = Vec::new();
+    write_turtle(&ctx, &graph.triples, &mut turtle).unwrap();
+
+    for to in [
+        Serialization::Turtle,
+        Serialization::NQuads,
+        Serialization::JsonLd,
+    ] {
+        let mut converted = Vec::new();
+        convert(&turtle[..], Serialization::Turtle, to, &mut converted).unwrap();
+
+        let mut back = Vec::new();
+        convert(&converted[..], to, Serialization::Turtle, &mut back).unwrap();
+        assert_round_trip(&graph.triples, &back, GraphFormat::Turtle);
+    }
+}
+
+#[test]
+fn convert_drops_graph_names() {
+    let quads = b"<http://example.com/a> <http://example.com/b> <http://example.com/c> <http://example.com/g> .\n";
+
+    let mut out = Vec::new();
+    convert(
+        &quads[..],
+        Serialization::NQuads,
+        Serialization::NQuads,
+        &mut out,
+    )
+    .unwrap();
+
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "<http://example.com/a> <http://example.com/b> <http://example.com/c> .\n"
+    );
+}
+
+#[test]
+fn convert_rejects_invalid_documents() {
+    let invalid: [(&[u8], Serialization); 3] = [
+        (
+            b"<http://example.com/a> <http://example.com/b> .",
+            Serialization::Turtle,
+        ),
+        (b"<http://example.com/a> .", Serialization::NQuads),
+        (b"{\"@id\": ", Serialization::JsonLd),
+    ];
+
+    for (data, from) in invalid {
+        let mut out = Vec::new();
+        assert!(
+            convert(data, from, Serialization::NQuads, &mut out).is_err(),
+            "{:?} parsed",
+            from
+        );
+    }
+}
+
+#[test]
+fn serializations_by_content_type() {
+    for serialization in [
+        Serialization::Turtle,
+        Serialization::NQuads,
+        Serialization::JsonLd,
+    ] {
+        assert_eq!(
+            Serialization::from_mime(serialization.mime()),
+            Some(serialization)
+        );
+    }
+
+    assert_eq!(
+        Serialization::from_mime("text/turtle; charset=UTF-8"),
+        Some(Serialization::Turtle)
+    );
+    assert_eq!(
+        Serialization::from_mime("application/n-triples"),
+        Some(Serialization::NQuads)
+    );
+    assert_eq!(Serialization::from_mime("application/octet-stream"), None);
+    assert_eq!(Serialization::from_mime(""), None);
+}
@@ -0,0 +1,294 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+use oxigraph::model::vocab::{rdf, xsd};
+use oxigraph::model::{BlankNode, Literal, NamedNode, NamedNodeRef, Subject, Term, Triple};
+
+use super::prefix::Prefix;
+use super::RdfContext;
+
+const INDENT: &str = "    ";
+
+type Properties<'a> = Vec<(&'a NamedNode, Vec<&'a Term>)>;
+
+/// Writes the triples as turtle.
+///
+/// Subjects keep the order in which they first appear, blank nodes that are referenced once are
+/// written inline and well formed rdf collections are written as `( ... )`.
+pub fn write_turtle(ctx: &RdfContext, triples: &[Triple], out: &mut impl Write) -> io::Result<()> {
+    let mut seen = HashSet::new();
+    let mut order: Vec<&Subject> = Vec::new();
+    let mut subjects: HashMap<Subject, Properties> = HashMap::new();
+    let mut references: HashMap<&BlankNode, usize> = HashMap::new();
+
+    for triple in triples.iter().filter(|x| seen.insert(*x)) {
+        let properties = subjects.entry(triple.subject.clone()).or_insert_with(|| {
+            order.push(&triple.subject);
+            Vec::new()
+        });
+
+        match properties.iter_mut().find(|(p, _)| *p == &triple.predicate) {
+            Some((_, objects)) => objects.push(&triple.object),
+            None => properties.push((&triple.predicate, vec![&triple.object])),
+        }
+
+        if let Term::BlankNode(ref node) = triple.object {
+            *references.entry(node).or_default() += 1;
+        }
+    }
+
+    let mut inline: HashSet<&BlankNode> = references
+        .iter()
+        .filter(|(_, count)| **count == 1)
+        .map(|(node, _)| *node)
+        .collect();
+
+    // Blank nodes that only reference each other are never reached from a written subject,
+    // these are written on their own
+    let mut reached = HashSet::new();
+    for subject in &order {
+        if !matches!(subject, Subject::BlankNode(node) if inline.contains(node)) {
+            mark_reached(subject, &subjects, &inline, &mut reached);
+        }
+    }
+    for subject in &order {
+        if let Subject::BlankNode(node) = *subject {
+            if inline.contains(node) && !reached.contains(node) {
+                inline.remove(node);
+                mark_reached(subject, &subjects, &inline, &mut reached);
+            }
+        }
+    }
+
+    let prefixes: Vec<Prefix> = ctx.prefixes().copied().collect();
+
+    let writer = TurtleWriter {
+        prefixes,
+        subjects: &subjects,
+        references: &references,
+        inline: &inline,
+    };
+
+    writer.write(&order, out)
+}
+
+fn mark_reached<'a>(
+    subject: &Subject,
+    subjects: &HashMap<Subject, Properties<'a>>,
+    inline: &HashSet<&'a BlankNode>,
+    reached: &mut HashSet<&'a BlankNode>,
+) {
+    for (_, objects) in subjects.get(subject).into_iter().flatten() {
+        for object in objects {
+            if let Term::BlankNode(node) = *object {
+                if inline.contains(node) && reached.insert(node) {
+                    mark_reached(&Subject::from(node.clone()), subjects, inline, reached);
+                }
+            }
+        }
+    }
+}
+
+struct TurtleWriter<'a, 'b> {
+    prefixes: Vec<Prefix>,
+    subjects: &'b HashMap<Subject, Properties<'a>>,
+    references: &'b HashMap<&'a BlankNode, usize>,
+    inline: &'b HashSet<&'a BlankNode>,
+}
+
+impl<'a, 'b> TurtleWriter<'a, 'b> {
+    fn write(&self, order: &[&Subject], out: &mut impl Write) -> io::Result<()> {
+        for (prefix, namespace) in &self.prefixes {
+            writeln!(out, "@prefix {}: <{}> .", prefix, namespace)?;
+        }
+        if !self.prefixes.is_empty() {
+            writeln!(out)?;
+        }
+
+        for subject in order {
+            let name = match subject {
+                Subject::NamedNode(node) => self.compact(node.as_str()),
+                Subject::BlankNode(node) if self.inline.contains(node) => continue,
+                Subject::BlankNode(node) if !self.references.contains_key(node) => "[]".into(),
+                Subject::BlankNode(node) => format!("_:{}", node.as_str()),
+                other => other.to_string(),
+            };
+
+            write!(out, "{} ", name)?;
+            self.write_properties(&self.subjects[*subject], 1, out)?;
+            write!(out, " .\n\n")?;
+        }
+
+        Ok(())
+    }
+
+    fn write_properties(
+        &self,
+        properties: &Properties<'a>,
+        depth: usize,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        for (i, (predicate, objects)) in properties.iter().enumerate() {
+            if i > 0 {
+                write!(out, ";\n{}", INDENT.repeat(depth))?;
+            }
+
+            if predicate.as_str() == rdf::TYPE.as_str() {
+                write!(out, "a ")?;
+            } else {
+                write!(out, "{} ", self.compact(predicate.as_str()))?;
+            }
+
+            for (j, object) in objects.iter().enumerate() {
+                if j > 0 {
+                    write!(out, ", ")?;
+                }
+                self.write_object(object, depth, out)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_object(&self, object: &Term, depth: usize, out: &mut impl Write) -> io::Result<()> {
+        match object {
+            Term::NamedNode(node) if node.as_str() == rdf::NIL.as_str() => write!(out, "()"),
+            Term::NamedNode(node) => write!(out, "{}", self.compact(node.as_str())),
+            Term::BlankNode(node) if self.inline.contains(node) => {
+                if let Some(items) = self.as_list(node) {
+                    write!(out, "(")?;
+                    for item in items {
+                        write!(out, " ")?;
+                        self.write_object(item, depth, out)?;
+                    }
+                    return write!(out, " )");
+                }
+
+                match self.subjects.get(&Subject::from(node.clone())) {
+                    Some(properties) => {
+                        write!(out, "[\n{}", INDENT.repeat(depth + 1))?;
+                        self.write_properties(properties, depth + 1, out)?;
+                        write!(out, "\n{}]", INDENT.repeat(depth))
+                    }
+                    None => write!(out, "[]"),
+                }
+            }
+            Term::BlankNode(node) => write!(out, "_:{}", node.as_str()),
+            Term::Literal(literal) => write!(out, "{}", self.literal(literal)),
+            other => write!(out, "{}", other),
+        }
+    }
+
+    /// The items of the collection starting at this node, if it is one
+    fn as_list(&self, head: &BlankNode) -> Option<Vec<&'a Term>> {
+        let mut items = Vec::new();
+        let mut current = head;
+
+        loop {
+            let properties = self.subjects.get(&Subject::from(current.clone()))?;
+            if properties.len() != 2 {
+                return None;
+            }
+
+            let object = |predicate: NamedNodeRef| match properties
+                .iter()
+                .find(|(p, _)| p.as_str() == predicate.as_str())
+            {
+                Some((_, objects)) if objects.len() == 1 => Some(objects[0]),
+                _ => None,
+            };
+
+            items.push(object(rdf::FIRST)?);
+
+            match object(rdf::REST)? {
+                Term::NamedNode(node) if node.as_str() == rdf::NIL.as_str() => return Some(items),
+                Term::BlankNode(node) if self.inline.contains(node) && node != head => {
+                    current = node
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Prefixed name of the iri, using the longest namespace that gives a valid local name
+    fn compact(&self, iri: &str) -> String {
+        self.prefixes
+            .iter()
+            .filter(|(_, namespace)| iri.starts_with(namespace))
+            .filter(|(_, namespace)| is_pn_local(&iri[namespace.len()..]))
+            .max_by_key(|(_, namespace)| namespace.len())
+            .map(|(prefix, namespace)| format!("{}:{}", prefix, &iri[namespace.len()..]))
+            .unwrap_or_else(|| format!("<{}>", iri))
+    }
+
+    fn literal(&self, literal: &Literal) -> String {
+        let value = literal.value();
+        let datatype = literal.datatype();
+
+        if let Some(language) = literal.language() {
+            return format!("\"{}\"@{}", escape(value), language);
+        }
+
+        if datatype == xsd::STRING {
+            return format!("\"{}\"", escape(value));
+        }
+
+        let bare = (datatype == xsd::INTEGER && is_integer(value))
+            || (datatype == xsd::BOOLEAN && matches!(value, "true" | "false"));
+        if bare {
+            return value.to_string();
+        }
+
+        format!("\"{}\"^^{}", escape(value), self.compact(datatype.as_str()))
+    }
+}
+
+fn is_integer(value: &str) -> bool {
+    let digits = value.strip_prefix(['+', '-']).unwrap_or(value);
+    !digits.is_empty() && digits.bytes().all(|x| x.is_ascii_digit())
+}
+
+/// Whether the local part of a prefixed name can be written without escapes
+fn is_pn_local(local: &str) -> bool {
+    if local.ends_with('.') {
+        return false;
+    }
+
+    let bytes = local.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            x if x.is_ascii_alphanumeric() => {}
+            b'_' | b':' => {}
+            b'-' | b'.' if i > 0 => {}
+            b'%' if i + 2 < bytes.len()
+                && bytes[i + 1].is_ascii_hexdigit()
+                && bytes[i + 2].is_ascii_hexdigit() =>
+            {
+                i += 2
+            }
+            _ => return false,
+        }
+        i += 1;
+    }
+
+    true
+}
+
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
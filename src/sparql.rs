@@ -1,9 +1,10 @@
 use std::{collections::HashMap, ops::Deref};
 
+use crate::models::BundleDTO;
 use derive::Query;
 use oxigraph::{
-    model::Term,
-    sparql::{QueryResults, QuerySolution},
+    model::{GraphName, NamedNode, Term},
+    sparql::{EvaluationError, QueryResults, QuerySolution},
     store::Store,
 };
 
@@ -77,14 +78,35 @@ where
     }
 }
 
+#[derive(Clone, Debug, Query)]
+pub struct BundleSolution {
+    pub group: QueryString<"bundle_group">,
+    pub artifact: QueryString<"bundle_artifact">,
+    pub version: QueryString<"bundle_version">,
+}
+
+impl From<BundleSolution> for BundleDTO {
+    fn from(this: BundleSolution) -> Self {
+        BundleDTO {
+            group: this.group.0,
+            artifact: this.artifact.0,
+            version: this.version.0,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Query)]
 pub struct QuerySolutionOutput {
     pub subject: QueryField<Term, "subject">,
 
+    pub path: QueryField<NamedNode, "p">,
     pub nifi_key: Option<QueryString<"nifi_key">>,
     pub value: QueryField<Term, "value">,
     pub shacl_type: ShaclType,
     pub ty: QueryString<"ty">,
+    pub bundle: Option<BundleSolution>,
+    /// Bundle version requested in the input
+    pub pinned_version: Option<QueryString<"pinned_version">>,
 }
 
 pub struct ProcessorQuery;
@@ -121,6 +143,14 @@ PREFIX : <https://w3id.org/conn#>
         ]
       ].
     }
+    OPTIONAL {
+      ?tys nifi:bundle [
+        nifi:bundleGroup ?bundle_group;
+        nifi:bundleArtifact ?bundle_artifact;
+        nifi:bundleVersion ?bundle_version;
+      ].
+    }
+    OPTIONAL { ?subject nifi:bundleVersion ?pinned_version }
   }
 "#;
 
@@ -133,6 +163,36 @@ impl WithSubject for QuerySolutionOutput {
     }
 }
 
+#[derive(Debug, Query)]
+pub struct TypeVersionOutput {
+    pub subject: QueryField<Term, "subject">,
+    /// Class of the type in one bundle
+    pub version: QueryField<NamedNode, "version">,
+    pub bundle_version: QueryString<"bundle_version">,
+    pub pinned_version: Option<QueryString<"pinned_version">>,
+}
+
+/// The bundle versions of the type of every processor
+pub struct TypeVersionQuery;
+impl Queryable for TypeVersionQuery {
+    const ERROR: &'static str = "Type version query";
+    const QUERY: &'static str = r#"
+PREFIX nifi: <https://w3id.org/conn/nifi#>
+PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#>
+SELECT DISTINCT ?subject ?version ?bundle_version ?pinned_version WHERE {
+    ?version a nifi:NifiProcess;
+      rdfs:subClassOf ?ty;
+      nifi:bundle [ nifi:bundleVersion ?bundle_version ].
+
+    ?subject a ?ty.
+    FILTER NOT EXISTS { ?subject a [ a nifi:NifiProcess ] }
+
+    OPTIONAL { ?subject nifi:bundleVersion ?pinned_version }
+}
+"#;
+    type Output = TypeVersionOutput;
+}
+
 type St = &'static str;
 
 #[derive(Clone, Debug, PartialEq, PartialOrd, Ord, Eq, Hash)]
@@ -174,6 +234,15 @@ impl FromTerm for String {
     }
 }
 
+impl FromTerm for NamedNode {
+    fn from_term(this: &Term) -> Result<Self, &'static str> {
+        match this {
+            Term::NamedNode(n) => Ok(n.clone()),
+            _ => Err("Expected named node"),
+        }
+    }
+}
+
 impl<T, const KEY: &'static str> FromQuery for QueryField<T, KEY>
 where
     T: FromTerm,
@@ -184,11 +253,15 @@ where
     }
 }
 
+/// A writer and a reader of a `nifi:NifiChannel`, a channel with many writers or readers has a
+/// row for every pair
 #[derive(Debug, Query)]
-pub struct NifiLinkQueryOutput<Rest> {
+pub struct NifiChannelLinkOutput {
+    pub channel: QueryField<Term, "channel">,
     pub source_id: QueryString<"source_id">,
     pub target_id: QueryString<"target_id">,
-    pub key: Rest,
+    pub key: QueryString<"key">,
+    pub serialization: Option<QueryField<NamedNode, "serialization">>,
 }
 
 pub struct NifiLinkQuery;
@@ -201,19 +274,19 @@ PREFIX fno: <https://w3id.org/function/ontology#>
 PREFIX fnom: <https://w3id.org/function/vocabulary/mapping#>
 PREFIX : <https://w3id.org/conn#> 
                 
-SELECT * WHERE {
-    _:channel a nifi:NifiChannel;
+SELECT DISTINCT ?channel ?source_id ?target_id ?key ?serialization WHERE {
+    ?channel a nifi:NifiChannel;
       :reader ?reader;
       :writer ?writer.
 
     ?sourceTy a nifi:NifiProcess.
-    [] sh:targetClass ?sourceType;
+    [] sh:targetClass ?sourceTy;
        sh:property [
          sh:class :WriterChannel;
          sh:path ?sourcePath;
        ].
 
-    ?sourceType nifi:mapping [
+    ?sourceTy nifi:mapping [
         fno:parameterMapping [
           fnom:functionParameter ?sourcePath;
           fnom:implementationParameterPosition ?key;
@@ -235,18 +308,130 @@ SELECT * WHERE {
    _:target a ?targetTy;
      <http://example.com/ns#testing+id> ?target_id;
      ?targetPath ?reader.
+
+   OPTIONAL { ?channel :serialization ?serialization }
+}
+"#;
+    type Output = NifiChannelLinkOutput;
+}
+
+/// A processor of a reader or writer node with a serialization, its instance converts the flow
+/// files. Nifi channels convert them on the instance of the writer
+#[derive(Debug, Query)]
+pub struct ConvertingProcessorOutput {
+    pub subject: QueryField<Term, "subject">,
+    pub serialization: QueryField<NamedNode, "serialization">,
+}
+
+pub struct ConvertingProcessorQuery;
+impl Queryable for ConvertingProcessorQuery {
+    const ERROR: &'static str = "Converting processor query";
+    const QUERY: &'static str = r#"
+PREFIX nifi: <https://w3id.org/conn/nifi#>
+PREFIX : <https://w3id.org/conn#>
+
+SELECT DISTINCT ?subject ?serialization WHERE {
+    VALUES ?end { :reader :writer }
+    ?channel ?end ?node.
+    ?subject ?path ?node.
+    FILTER NOT EXISTS { ?channel a nifi:NifiChannel; :reader ?node }
+
+    OPTIONAL { ?node :serialization ?node_serialization }
+    OPTIONAL { ?channel :serialization ?channel_serialization }
+    BIND (COALESCE(?node_serialization, ?channel_serialization) AS ?serialization)
+    FILTER (BOUND(?serialization))
+}
+"#;
+    type Output = ConvertingProcessorOutput;
+}
+
+#[derive(Debug, Query)]
+pub struct UsedTypeOutput {
+    pub ty: QueryField<NamedNode, "ty">,
+}
+
+/// All nifi types that are used in the store
+pub struct UsedTypesQuery;
+impl Queryable for UsedTypesQuery {
+    const ERROR: &'static str = "Used types query";
+    const QUERY: &'static str = r#"
+PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#>
+SELECT DISTINCT ?ty WHERE {
+    ?subject a/rdfs:subClassOf* ?ty.
+    FILTER(STRSTARTS(STR(?ty), "https://w3id.org/conn/nifi#"))
 }
 "#;
-    type Output = NifiLinkQueryOutput<QueryString<"key">>;
+    type Output = UsedTypeOutput;
+}
+
+/// The graphs of the store that together form the default graph of the queries
+pub struct Dataset<'a> {
+    pub store: &'a Store,
+    pub graphs: Vec<GraphName>,
+}
+
+impl<'a> Dataset<'a> {
+    pub fn query(&self, query: &str) -> Result<QueryResults, EvaluationError> {
+        let mut query = oxigraph::sparql::Query::parse(query, None)?;
+        query.dataset_mut().set_default_graph(self.graphs.clone());
+        self.store.query(query)
+    }
+
+    /// Runs `INSERT { GRAPH <graph> { insert } } WHERE { pattern }` over the dataset
+    fn insert(&self, graph: &str, insert: &str, pattern: &str) -> Result<(), EvaluationError> {
+        let using: String = self
+            .graphs
+            .iter()
+            .map(|x| format!("USING {}\n", x))
+            .collect();
+
+        let update = format!(
+            "PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#>\n\
+             INSERT {{ GRAPH <{}> {{ {} }} }}\n{}WHERE {{ {} }}",
+            graph, insert, using, pattern
+        );
+        self.store.update(update.as_str())
+    }
+}
+
+const SUB_PROPERTY_UPDATE: (&str, &str) = (
+    "?s ?super ?o",
+    r#"
+    ?p rdfs:subPropertyOf+ ?super.
+    ?s ?p ?o.
+    FILTER(?p != ?super)
+"#,
+);
+
+const SUB_CLASS_UPDATE: (&str, &str) = (
+    "?s a ?super",
+    r#"
+    ?ty rdfs:subClassOf+ ?super.
+    ?s a ?ty.
+    FILTER(?ty != ?super)
+"#,
+);
+
+/// Adds the statements entailed by `rdfs:subPropertyOf` and `rdfs:subClassOf` to `graph`, so the
+/// queries also match properties and instances of classes that extend the ontology
+pub fn materialize_rdfs(dataset: &Dataset, graph: &str) {
+    let (insert, pattern) = SUB_PROPERTY_UPDATE;
+    dataset
+        .insert(graph, insert, pattern)
+        .expect("Materialize rdfs:subPropertyOf");
+    let (insert, pattern) = SUB_CLASS_UPDATE;
+    dataset
+        .insert(graph, insert, pattern)
+        .expect("Materialize rdfs:subClassOf");
 }
 
-pub fn execute_query<T: Queryable>(store: &Store) -> Vec<T::Output>
+pub fn execute_query<T: Queryable>(dataset: &Dataset) -> Vec<T::Output>
 where
     T::Output: FromQuery,
 {
     println!("Exectuting query {}", stringify!(T));
 
-    if let QueryResults::Solutions(solutions) = store.query(T::QUERY).unwrap() {
+    if let QueryResults::Solutions(solutions) = dataset.query(T::QUERY).unwrap() {
         solutions
             .flatten()
             .flat_map(|s| match T::Output::from_query(Sol(&s)) {
@@ -262,14 +447,14 @@ where
     }
 }
 
-pub fn get_parameter_solutions<T: Queryable>(store: &Store) -> HashMap<Term, Vec<T::Output>>
+pub fn get_parameter_solutions<T: Queryable>(dataset: &Dataset) -> HashMap<Term, Vec<T::Output>>
 where
     T::Output: FromQuery,
     T::Output: WithSubject,
 {
     let mut per_subject: HashMap<Term, Vec<T::Output>> = HashMap::new();
 
-    if let QueryResults::Solutions(solutions) = store.query(T::QUERY).unwrap() {
+    if let QueryResults::Solutions(solutions) = dataset.query(T::QUERY).unwrap() {
         for sol in solutions.flatten() {
             let param = match T::Output::from_query(Sol(&sol)) {
                 Ok(x) => x,
@@ -65,6 +65,14 @@ impl RDFMapper {
         }
     }
 
+    // #[inline]
+    pub fn map_graph_name<'a>(&mut self, graph: &'a rm::GraphName<'a>) -> GraphName {
+        match graph {
+            rm::GraphName::NamedNode(n) => GraphName::NamedNode(self.map_predicate(n)),
+            rm::GraphName::BlankNode(n) => GraphName::BlankNode(self.blank_node(n.id)),
+        }
+    }
+
     // #[inline]
     pub fn map_triple_to_quad(
         &mut self,
@@ -73,12 +81,13 @@ impl RDFMapper {
             predicate,
             object,
         }: rm::Triple,
+        graph: GraphName,
     ) -> Quad {
         Quad::new(
             self.map_subject(&subject),
             self.map_predicate(&predicate),
             self.map_object(&object),
-            GraphName::DefaultGraph,
+            graph,
         )
     }
 }